@@ -0,0 +1,54 @@
+output = Output
+input = Input
+no-device = No device
+sound-settings = Sound settings...
+unknown-artist = Unknown artist
+album-track = { $album } (#{ $track })
+# Translators: placement of the percent sign and any spacing around it
+volume-percent = { $volume }%
+battery-level = Battery { $level }
+device-switched = Switched to { $device }
+undo = Undo
+mute-output = Mute output
+unmute-output = Unmute output
+mute-input = Mute input
+unmute-input = Unmute input
+previous-track = Previous track
+next-track = Next track
+play = Play
+pause = Pause
+stop = Stop
+refresh-devices = Refresh devices
+back = Back
+reconnecting = Reconnecting to audio…
+detecting-devices = Detecting devices…
+no-devices-found = No devices found
+volume-down = Lower volume
+volume-up = Raise volume
+shuffle = Shuffle
+repeat = Repeat
+repeat-track = Repeat track
+copied = Copied
+output-settings = Output settings
+input-settings = Input settings
+combine-outputs = Play on multiple outputs…
+play-on-selected = Play on selected
+cancel = Cancel
+mic-boost = Boost microphone
+mic-boost-off = Restore microphone level
+sound = Sound
+output-switched = Output switched to { $device }
+no-media-playing = No media playing
+# Translators: current playback speed, e.g. 1.25×
+playback-rate = { $rate }×
+playback-speed = Playback speed
+confirm-boost = Volume above 100% may distort or harm hearing.
+allow-boost = Allow
+output-clipping = Output is clipping; lower the volume to avoid distortion
+rename-device = Rename device
+volume-change-failed = Volume change didn't apply
+scroll-tip = Tip: scroll over the panel icon to change the volume.
+dismiss = Dismiss
+controlling-app = Controlling { $app }
+meter-combined = One bar
+meter-per-channel = Bar per channel
@@ -0,0 +1,29 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Accent colors picked from album art.
+
+use cosmic::iced::Color;
+use std::path::PathBuf;
+
+// Art is sampled at this size; the exact pixels don't matter for an average
+const SAMPLE_SIZE: u32 = 32;
+
+/// The art's dominant color, favouring saturated pixels over greys; `None` when the file
+/// can't be decoded or is nearly colorless.
+pub async fn dominant_color(path: PathBuf) -> Option<Color> {
+    tokio::task::spawn_blocking(move || {
+        let art = image::open(&path).ok()?.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+        let (mut sum, mut weight) = ([0.0f32; 3], 0.0f32);
+        for pixel in art.pixels() {
+            let [r, g, b] = pixel.0.map(|c| c as f32 / 255.0);
+            let saturation = r.max(g).max(b) - r.min(g).min(b);
+            sum = [sum[0] + r * saturation, sum[1] + g * saturation, sum[2] + b * saturation];
+            weight += saturation;
+        }
+        (weight > 1.0).then(|| Color::from_rgb(sum[0] / weight, sum[1] / weight, sum[2] / weight))
+    })
+    .await
+    .ok()
+    .flatten()
+}
@@ -0,0 +1,66 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Small time-based transitions for the popup, advanced by `Message::Frame`.
+
+use cosmic_time::Instant;
+use std::time::Duration;
+
+/// Interval between `Frame` ticks while a transition is running.
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    start: Instant,
+    duration: Duration,
+}
+
+impl Progress {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            duration,
+        }
+    }
+
+    /// Eased (cubic ease-out) progress in `0.0..=1.0` at `now`.
+    pub fn at(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let t = (now.saturating_duration_since(self.start).as_secs_f32()
+            / self.duration.as_secs_f32())
+        .clamp(0.0, 1.0);
+        1.0 - (1.0 - t).powi(3)
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= self.duration
+    }
+}
+
+/// Interpolates between two values over a `Progress`.
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+    from: f32,
+    to: f32,
+    progress: Progress,
+}
+
+impl Tween {
+    pub fn new(from: f32, to: f32, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            progress: Progress::new(duration),
+        }
+    }
+
+    pub fn value(&self, now: Instant) -> f32 {
+        self.from + (self.to - self.from) * self.progress.at(now)
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.progress.is_done(now)
+    }
+}
@@ -0,0 +1,22 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Album art resampled to the pixels it covers on screen.
+
+use cosmic::widget::image::Handle;
+use image::imageops::FilterType;
+use std::path::PathBuf;
+
+/// The art at `width` physical pixels, keeping its aspect ratio. The renderer's own
+/// scaling blurs or aliases at fractional scales, so it is given an exact fit instead.
+pub async fn scaled(path: PathBuf, width: u32) -> Option<Handle> {
+    tokio::task::spawn_blocking(move || {
+        let art = image::open(&path).ok()?;
+        let height = (art.height() as u64 * width as u64 / art.width().max(1) as u64).max(1) as u32;
+        let art = art.resize_exact(width, height, FilterType::Lanczos3).into_rgba8();
+        Some(Handle::from_rgba(width, height, art.into_raw()))
+    })
+    .await
+    .ok()
+    .flatten()
+}
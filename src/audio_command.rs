@@ -0,0 +1,199 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Volume and mute changes carried out by `wpctl`, with failures reported as
+//! [`AudioError`]s, and a queue running them one at a time.
+
+use std::{fmt, process::Output};
+
+use cosmic::{
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, StreamExt, channel::mpsc},
+};
+use tokio::process::Command;
+
+/// The node an [`AudioCommand`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    DefaultSink,
+    DefaultSource,
+    Node(u32),
+}
+
+impl Target {
+    fn arg(self) -> String {
+        match self {
+            Self::DefaultSink => "@DEFAULT_AUDIO_SINK@".to_owned(),
+            Self::DefaultSource => "@DEFAULT_AUDIO_SOURCE@".to_owned(),
+            Self::Node(id) => id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCommand {
+    /// Volume in percent.
+    SetVolume(Target, u32),
+    SetMute(Target, bool),
+    ToggleMute(Target),
+}
+
+impl AudioCommand {
+    fn args(self) -> Vec<String> {
+        match self {
+            Self::SetVolume(target, volume) => vec!["set-volume".to_owned(), target.arg(), format!("{:.2}", volume as f32 / 100.0)],
+            Self::SetMute(target, muted) => vec!["set-mute".to_owned(), target.arg(), if muted { "1" } else { "0" }.to_owned()],
+            Self::ToggleMute(target) => vec!["set-mute".to_owned(), target.arg(), "toggle".to_owned()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioError {
+    /// `wpctl` could not be started, e.g. because WirePlumber is not installed.
+    Spawn(String),
+    /// `wpctl` ran but rejected the command, e.g. for a node that no longer exists.
+    Failed { code: Option<i32>, stderr: String },
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawn(err) => write!(f, "failed to run wpctl: {err}"),
+            Self::Failed { code: Some(code), stderr } => write!(f, "wpctl exited with {code}: {stderr}"),
+            Self::Failed { code: None, stderr } => write!(f, "wpctl was killed: {stderr}"),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Run `command`, waiting for `wpctl` to exit.
+pub async fn run(command: AudioCommand) -> Result<(), AudioError> {
+    run_with(command, |args| async move { Command::new("wpctl").args(args).output().await }).await
+}
+
+// `exec` runs `wpctl` with the given arguments
+async fn run_with<F, Fut>(command: AudioCommand, exec: F) -> Result<(), AudioError>
+where
+    F: FnOnce(Vec<String>) -> Fut,
+    Fut: Future<Output = std::io::Result<Output>>,
+{
+    let output = exec(command.args()).await.map_err(|err| AudioError::Spawn(err.to_string()))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(AudioError::Failed {
+        code: output.status.code(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+    })
+}
+
+/// Start `command` without waiting for it, for when no [`Queue`] is available.
+pub fn spawn(command: AudioCommand) -> Result<(), AudioError> {
+    std::process::Command::new("wpctl").args(command.args()).spawn().map(drop).map_err(|err| AudioError::Spawn(err.to_string()))
+}
+
+pub type Queue = mpsc::UnboundedSender<AudioCommand>;
+
+#[derive(Debug, Clone)]
+pub enum QueueEvent {
+    /// The queue is running and takes commands.
+    Ready(Queue),
+    Failed(AudioCommand, AudioError),
+}
+
+/// Runs commands sent to the [`Queue`] it yields in order, each after the last has exited.
+///
+/// Separately spawned processes can finish in any order, so an older volume could
+/// otherwise land after a newer one.
+pub fn queue() -> iced::Subscription<QueueEvent> {
+    Subscription::run_with_id(
+        "audio-command-queue",
+        stream::channel(8, |mut output| async move {
+            let (tx, mut rx) = mpsc::unbounded();
+            let _ = output.send(QueueEvent::Ready(tx)).await;
+            while let Some(first) = rx.next().await {
+                let mut pending = vec![first];
+                while let Ok(Some(command)) = rx.try_next() {
+                    pending.push(command);
+                }
+                for command in coalesce(pending) {
+                    if let Err(err) = run(command).await {
+                        let _ = output.send(QueueEvent::Failed(command, err)).await;
+                    }
+                }
+            }
+            futures::future::pending().await
+        }),
+    )
+}
+
+// Only the newest volume for each node matters, e.g. from a burst of scroll steps
+fn coalesce(pending: Vec<AudioCommand>) -> Vec<AudioCommand> {
+    let superseded = |i: usize, target: Target| {
+        pending[i + 1..].iter().any(|c| matches!(c, AudioCommand::SetVolume(t, _) if *t == target))
+    };
+    pending
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| !matches!(c, AudioCommand::SetVolume(t, _) if superseded(*i, *t)))
+        .map(|(_, c)| *c)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic::iced_futures::futures::executor::block_on;
+    use std::{io, os::unix::process::ExitStatusExt, process::ExitStatus};
+
+    // What `wpctl` would leave behind, given a raw wait status
+    fn exited(status: i32, stderr: &str) -> io::Result<Output> {
+        Ok(Output { status: ExitStatus::from_raw(status), stdout: Vec::new(), stderr: stderr.as_bytes().to_vec() })
+    }
+
+    #[test]
+    fn passes_the_command_arguments() {
+        let mut seen = Vec::new();
+        let result = block_on(run_with(AudioCommand::SetVolume(Target::Node(40), 25), |args| {
+            seen = args;
+            async { exited(0, "") }
+        }));
+        assert_eq!(result, Ok(()));
+        assert_eq!(seen, ["set-volume", "40", "0.25"]);
+    }
+
+    #[test]
+    fn rejected_commands_keep_the_exit_code_and_stderr() {
+        // Exit code 1 sits in the second byte of the wait status
+        let result = block_on(run_with(AudioCommand::ToggleMute(Target::Node(40)), |_| async { exited(1 << 8, "Object not found\n") }));
+        assert_eq!(result, Err(AudioError::Failed { code: Some(1), stderr: "Object not found".to_owned() }));
+    }
+
+    #[test]
+    fn killed_commands_have_no_exit_code() {
+        let result = block_on(run_with(AudioCommand::ToggleMute(Target::DefaultSink), |_| async { exited(9, "") }));
+        assert_eq!(result, Err(AudioError::Failed { code: None, stderr: String::new() }));
+    }
+
+    #[test]
+    fn commands_that_cannot_start_report_spawn() {
+        let result = block_on(run_with(AudioCommand::SetMute(Target::DefaultSource, true), |_| async {
+            Err(io::Error::new(io::ErrorKind::NotFound, "No such file or directory"))
+        }));
+        assert_eq!(result, Err(AudioError::Spawn("No such file or directory".to_owned())));
+    }
+
+    #[test]
+    fn keeps_the_newest_volume_per_target() {
+        let (a, b) = (Target::Node(40), Target::Node(41));
+        let pending = vec![
+            AudioCommand::SetVolume(a, 10),
+            AudioCommand::SetMute(a, true),
+            AudioCommand::SetVolume(a, 20),
+            AudioCommand::SetVolume(b, 5),
+        ];
+        assert_eq!(coalesce(pending), [AudioCommand::SetMute(a, true), AudioCommand::SetVolume(a, 20), AudioCommand::SetVolume(b, 5)]);
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Paired Bluetooth audio devices, queried through `bluetoothctl`.
+
+use cosmic::iced_futures::futures::future::join_all;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BtDevice {
+    pub address: String,
+    pub name: String,
+    pub connected: bool,
+}
+
+/// Paired devices whose Bluetooth class is audio (headsets, speakers, ...).
+pub async fn audio_devices() -> Vec<BtDevice> {
+    let Some(paired) = bluetoothctl(&["devices", "Paired"]).await else {
+        return Vec::new();
+    };
+
+    let lookups = paired.lines().filter_map(|line| {
+        let (address, name) = line.strip_prefix("Device ")?.split_once(' ')?;
+        Some(device_info(address, name))
+    });
+    join_all(lookups).await.into_iter().flatten().collect()
+}
+
+/// The device when `bluetoothctl info` reports it as audio.
+async fn device_info(address: &str, name: &str) -> Option<BtDevice> {
+    let info = bluetoothctl(&["info", address]).await?;
+    let is_audio = info.lines().any(|l| {
+        l.trim()
+            .strip_prefix("Icon: ")
+            .is_some_and(|icon| icon.starts_with("audio-"))
+    });
+    is_audio.then(|| BtDevice {
+        address: address.to_owned(),
+        name: name.to_owned(),
+        connected: info.lines().any(|l| l.trim() == "Connected: yes"),
+    })
+}
+
+pub async fn connect(address: String) -> bool {
+    bluetoothctl(&["connect", &address]).await.is_some()
+}
+
+async fn bluetoothctl(args: &[&str]) -> Option<String> {
+    let output = Command::new("bluetoothctl").args(args).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -5,11 +5,23 @@ use cosmic::cosmic_config::{
     self, Config, ConfigGet, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-const AUDIO_CONFIG: &str = "com.system76.CosmicAudio";
+pub const AUDIO_CONFIG: &str = "com.system76.CosmicAudio";
 const AMPLIFICATION_SINK: &str = "amplification_sink";
 const AMPLIFICATION_SOURCE: &str = "amplification_source";
 
+/// Layout revision of `AudioAppletConfig`'s stored fields, bumped when a field
+/// needs converting rather than just defaulting.
+pub const CONFIG_VERSION: u32 = 1;
+
+// Upper bounds for hand-edited values
+const MAX_SCROLL_STEP: u32 = 50;
+const MAX_SEEK_STEP: u32 = 300;
+const MAX_POPUP_WIDTH: u16 = 1200;
+/// The highest volume the sliders allow, with amplification enabled.
+const MAX_VOLUME: u32 = 150;
+
 pub fn amplification_sink() -> bool {
     Config::new(AUDIO_CONFIG, 1)
         .ok()
@@ -24,8 +36,385 @@ pub fn amplification_source() -> bool {
         .unwrap_or(false)
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+/// The Sound settings' amplification switches, watched so an open popup follows them.
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+#[version = 1]
+pub struct AmplificationConfig {
+    pub amplification_sink: bool,
+    pub amplification_source: bool,
+}
+
+impl Default for AmplificationConfig {
+    fn default() -> Self {
+        Self {
+            amplification_sink: true,
+            amplification_source: false,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MediaLayout {
+    /// Large art on top, then controls, title and artist.
+    #[default]
+    Vertical,
+    /// Small art, stacked title/artist and controls side by side.
+    Compact,
+}
+
+/// How muting the output is carried out.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MuteMode {
+    /// Toggle the sink's mute flag.
+    #[default]
+    Flag,
+    /// Set the volume to zero and restore it on unmute, for setups that mishandle the flag.
+    ZeroVolume,
+}
+
+/// What clicking the device that is already the default does.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ActiveDeviceClick {
+    /// Collapse the device list, or leave the device page.
+    #[default]
+    Collapse,
+    Nothing,
+}
+
+/// Order of the device lists.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DeviceSort {
+    /// As PipeWire lists them.
+    #[default]
+    Model,
+    /// By shown name, labels included.
+    Alphabetical,
+    /// Most recently selected first.
+    LastUsed,
+}
+
+/// How scroll and step adjustments map onto the volume.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ScrollCurve {
+    /// Every step changes the volume by `scroll_step` percentage points.
+    #[default]
+    Linear,
+    /// Steps sound about equally large, being smaller at low volumes.
+    Cubic,
+}
+
+/// Which device's controls the popup shows first.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PrimarySlider {
+    #[default]
+    Output,
+    Input,
+}
+
+/// How the input meter shows the channels it follows.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MeterLayout {
+    /// One bar at the loudest channel's level.
+    #[default]
+    Combined,
+    /// A bar for each channel.
+    PerChannel,
+}
+
+/// Modifier key held while clicking the panel icon.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClickModifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
+}
+
+/// What pressing the panel icon does.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PanelAction {
+    #[default]
+    TogglePopup,
+    ToggleMute,
+    OpenSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
 #[version = 1]
 pub struct AudioAppletConfig {
+    /// Revision the stored config was written with; missing means it predates versioning.
+    pub version: u32,
     pub show_media_controls_in_top_panel: bool,
+    pub primary_slider: PrimarySlider,
+    /// Bus name prefix of the player to control whenever it runs, e.g. `org.mpris.MediaPlayer2.spotify`.
+    pub preferred_player: Option<String>,
+    /// Show the playing track's title and a play/pause button beside the panel icon.
+    pub panel_media_mode: bool,
+    /// Use monochrome symbolic icons; otherwise the theme's full-color variants.
+    pub symbolic_icons: bool,
+    /// Mark the panel icon with a dot while sound plays through the output.
+    pub playing_indicator: bool,
+    /// Panel icon size in logical pixels, up to the panel's thickness; `None` uses the panel's size.
+    pub panel_icon_size: Option<u16>,
+    /// Last committed volume of each output, restored when it is selected again.
+    pub device_volumes: HashMap<String, u32>,
+    /// Names shown instead of the devices' own, keyed by device name.
+    pub device_labels: HashMap<String, String>,
+    pub device_sort: DeviceSort,
+    /// Output the main slider controls while it is present, instead of the default output.
+    pub pinned_sink: Option<String>,
+    /// Devices listed first, in this order, regardless of `device_sort`.
+    pub pinned_devices: Vec<String>,
+    /// Devices by when they were last selected, most recent first.
+    pub recent_devices: Vec<String>,
+    /// Seconds to seek per scroll step over the media progress bar.
+    pub seek_step: u32,
+    pub media_layout: MediaLayout,
+    /// Scroll overflowing titles back and forth instead of ellipsizing them.
+    pub marquee_titles: bool,
+    /// Briefly show a new track's title in the accent color.
+    pub track_change_flash: bool,
+    /// Tint the active media buttons and the progress bar with a color from the album art.
+    pub art_accent: bool,
+    /// Show a stop button next to play/pause for players that support it.
+    pub show_stop_button: bool,
+    /// Show the album (and track number) under the artist.
+    pub show_album: bool,
+    /// Show disabled controls and a "No media playing" caption while no player is active.
+    pub media_placeholder: bool,
+    /// List PipeWire monitor sources (loopbacks of outputs) among the inputs.
+    pub show_monitor_sources: bool,
+    /// Left-click action for the panel icon; right-click opens the popup otherwise.
+    pub primary_action: PanelAction,
+    /// Double-tapping the panel icon on a touchscreen toggles mute.
+    pub double_tap_mute: bool,
+    /// Clicking the panel icon with this modifier held opens Sound settings; `None` disables it.
+    pub settings_modifier: Option<ClickModifier>,
+    /// Fixed popup width in logical pixels; `None` sizes the popup to its content.
+    pub popup_width: Option<u16>,
+    /// Open the popup when hovering the panel icon, and close it once the cursor leaves.
+    pub open_on_hover: bool,
+    /// Volume percentage points per scroll step.
+    pub scroll_step: u32,
+    /// How scroll steps map onto the volume.
+    pub scroll_curve: ScrollCurve,
+    /// Scroll down to raise volume (and seek forward) instead of up.
+    pub invert_scroll: bool,
+    /// Unmute the output when a scroll or volume-up sets a non-zero level.
+    pub unmute_on_scroll: bool,
+    /// Whether the tip about scrolling over the panel icon was dismissed.
+    pub seen_scroll_tip: bool,
+    /// Ask once per session before raising the output above 100%.
+    pub confirm_boost: bool,
+    pub mute_mode: MuteMode,
+    /// Highest output and input volumes the sliders offer; `None` follows the amplification settings.
+    pub max_output_volume: Option<u32>,
+    pub max_input_volume: Option<u32>,
+    /// Input volume the microphone boost toggle jumps to, capped at 100% without amplification.
+    pub mic_boost_level: u32,
+    /// Mute the input while the session is locked or idle, unmuting it on return.
+    pub auto_mute_mic_on_idle: bool,
+    /// Open device lists as their own page instead of expanding them inline.
+    pub device_pages: bool,
+    pub active_device_click: ActiveDeviceClick,
+    /// Close the popup once a newly selected output or input is the default.
+    pub close_on_select: bool,
+    /// Show a desktop notification when the default output changes.
+    pub notify_on_device_change: bool,
+    /// Serve `GetVolume`, `SetVolume`, `ToggleMute`, `TogglePopup` and `CycleOutput` on the session bus.
+    pub dbus_control: bool,
+    /// Maximum output volume while the night schedule is active; `None` disables the cap.
+    pub night_cap: Option<u32>,
+    /// Local hours (0-23) the cap starts and ends at; equal hours cap all day.
+    pub night_cap_from: u8,
+    pub night_cap_until: u8,
+    /// Warn in the popup while the output's peaks reach full scale, by sampling it with `parec`.
+    pub clip_warning: bool,
+    /// Control the focused window's stream with the main slider while it plays one.
+    pub follow_focused_app: bool,
+    /// Show the input's level under its slider while the popup is open, sampled with `parec`.
+    pub input_meter: bool,
+    /// One bar for the followed channels or a bar for each.
+    pub input_meter_layout: MeterLayout,
+    /// Input channels the meter follows, counted from 0; empty follows all of them.
+    pub input_meter_channels: Vec<u8>,
+}
+
+impl AudioAppletConfig {
+    /// Upgrade a config written by an older version, returning whether it changed.
+    ///
+    /// Fields added since are already defaulted on load. Unversioned configs
+    /// only had `show_media_controls_in_top_panel`, which `panel_media_mode` replaced.
+    pub fn migrate(&mut self) -> bool {
+        if self.version >= CONFIG_VERSION {
+            return false;
+        }
+        if self.version == 0 && self.show_media_controls_in_top_panel {
+            self.panel_media_mode = true;
+        }
+        self.version = CONFIG_VERSION;
+        true
+    }
+
+    /// Reset out-of-range values, e.g. from hand edits, to their defaults.
+    pub fn validate(&mut self) {
+        let defaults = Self::default();
+        if !(1..=MAX_SCROLL_STEP).contains(&self.scroll_step) {
+            tracing::warn!("Invalid scroll_step {}, using {}", self.scroll_step, defaults.scroll_step);
+            self.scroll_step = defaults.scroll_step;
+        }
+        if !(1..=MAX_SEEK_STEP).contains(&self.seek_step) {
+            tracing::warn!("Invalid seek_step {}, using {}", self.seek_step, defaults.seek_step);
+            self.seek_step = defaults.seek_step;
+        }
+        if self.popup_width.is_some_and(|w| w > MAX_POPUP_WIDTH) {
+            tracing::warn!("Invalid popup_width {:?}, sizing to content", self.popup_width);
+            self.popup_width = defaults.popup_width;
+        }
+        if !(1..=MAX_VOLUME).contains(&self.mic_boost_level) {
+            tracing::warn!("Invalid mic_boost_level {}, using {}", self.mic_boost_level, defaults.mic_boost_level);
+            self.mic_boost_level = defaults.mic_boost_level;
+        }
+        if self.max_output_volume.is_some_and(|v| !(1..=MAX_VOLUME).contains(&v)) {
+            tracing::warn!("Invalid max_output_volume {:?}, following amplification", self.max_output_volume);
+            self.max_output_volume = defaults.max_output_volume;
+        }
+        if self.max_input_volume.is_some_and(|v| !(1..=MAX_VOLUME).contains(&v)) {
+            tracing::warn!("Invalid max_input_volume {:?}, following amplification", self.max_input_volume);
+            self.max_input_volume = defaults.max_input_volume;
+        }
+        if self.panel_icon_size == Some(0) {
+            tracing::warn!("Invalid panel_icon_size 0, using the panel's size");
+            self.panel_icon_size = defaults.panel_icon_size;
+        }
+        if self.night_cap.is_some_and(|v| v > MAX_VOLUME) {
+            tracing::warn!("Invalid night_cap {:?}, disabling it", self.night_cap);
+            self.night_cap = defaults.night_cap;
+        }
+        if self.night_cap_from > 23 || self.night_cap_until > 23 {
+            tracing::warn!("Invalid night cap hours {}-{}, using defaults", self.night_cap_from, self.night_cap_until);
+            self.night_cap_from = defaults.night_cap_from;
+            self.night_cap_until = defaults.night_cap_until;
+        }
+        for (device, volume) in &mut self.device_volumes {
+            if *volume > MAX_VOLUME {
+                tracing::warn!("Invalid remembered volume {volume} for {device}, clamping");
+                *volume = MAX_VOLUME;
+            }
+        }
+    }
+
+    /// Whether the night cap applies at local `hour`.
+    pub fn night_cap_applies(&self, hour: u8) -> bool {
+        let (from, until) = (self.night_cap_from, self.night_cap_until);
+        if from == until {
+            true
+        } else if from < until {
+            (from..until).contains(&hour)
+        } else {
+            hour >= from || hour < until
+        }
+    }
+}
+
+impl Default for AudioAppletConfig {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            show_media_controls_in_top_panel: false,
+            primary_slider: PrimarySlider::default(),
+            preferred_player: None,
+            panel_media_mode: false,
+            symbolic_icons: true,
+            playing_indicator: false,
+            panel_icon_size: None,
+            device_volumes: HashMap::new(),
+            device_labels: HashMap::new(),
+            device_sort: DeviceSort::default(),
+            pinned_sink: None,
+            pinned_devices: Vec::new(),
+            recent_devices: Vec::new(),
+            seek_step: 5,
+            media_layout: MediaLayout::default(),
+            marquee_titles: false,
+            track_change_flash: true,
+            art_accent: false,
+            show_stop_button: false,
+            show_album: true,
+            media_placeholder: false,
+            show_monitor_sources: false,
+            primary_action: PanelAction::default(),
+            double_tap_mute: false,
+            settings_modifier: Some(ClickModifier::Ctrl),
+            popup_width: None,
+            open_on_hover: false,
+            scroll_step: 5,
+            scroll_curve: ScrollCurve::default(),
+            invert_scroll: false,
+            unmute_on_scroll: true,
+            seen_scroll_tip: false,
+            confirm_boost: true,
+            mute_mode: MuteMode::default(),
+            max_output_volume: None,
+            max_input_volume: None,
+            mic_boost_level: 120,
+            auto_mute_mic_on_idle: false,
+            device_pages: false,
+            active_device_click: ActiveDeviceClick::default(),
+            close_on_select: false,
+            notify_on_device_change: false,
+            dbus_control: false,
+            night_cap: None,
+            night_cap_from: 22,
+            night_cap_until: 7,
+            clip_warning: false,
+            follow_focused_app: false,
+            input_meter: false,
+            input_meter_layout: MeterLayout::default(),
+            input_meter_channels: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What the applet stored before versioning: a single key, everything else defaulted on load
+    fn unversioned(show_media_controls_in_top_panel: bool) -> AudioAppletConfig {
+        AudioAppletConfig { show_media_controls_in_top_panel, ..Default::default() }
+    }
+
+    #[test]
+    fn upgrades_an_unversioned_config() {
+        let mut config = unversioned(true);
+        assert!(config.migrate());
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.panel_media_mode);
+        let expected = AudioAppletConfig {
+            version: CONFIG_VERSION,
+            show_media_controls_in_top_panel: true,
+            panel_media_mode: true,
+            ..Default::default()
+        };
+        assert_eq!(config, expected);
+
+        // Once upgraded there is nothing left to write back
+        assert!(!config.migrate());
+    }
+
+    #[test]
+    fn upgrade_keeps_panel_media_off_when_it_was_off() {
+        let mut config = unversioned(false);
+        assert!(config.migrate());
+        assert!(!config.panel_media_mode);
+    }
+
+    // A current config turning panel media off must not have the old key turn it back on
+    #[test]
+    fn current_config_is_left_alone() {
+        let mut config = AudioAppletConfig { version: CONFIG_VERSION, show_media_controls_in_top_panel: true, ..Default::default() };
+        assert!(!config.migrate());
+        assert!(!config.panel_media_mode);
+    }
 }
@@ -0,0 +1,77 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! An opt-in session bus endpoint for scripting volume, mute, outputs and the popup.
+
+use cosmic::{
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, channel::mpsc::Sender},
+};
+
+pub const BUS_NAME: &str = "com.usr.AudioApplet";
+const OBJECT_PATH: &str = "/com/usr/AudioApplet";
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    /// Output volume in percent.
+    SetVolume(u32),
+    ToggleMute,
+    TogglePopup,
+    /// Make the next output the default.
+    CycleOutput,
+}
+
+struct Control {
+    output: Sender<Request>,
+}
+
+#[zbus::interface(name = "com.usr.AudioApplet")]
+impl Control {
+    /// Output volume in percent, read from PipeWire rather than the applet's last known value.
+    async fn get_volume(&self) -> zbus::fdo::Result<u32> {
+        crate::wpctl::default_sink_volume()
+            .await
+            .ok_or_else(|| zbus::fdo::Error::Failed("Failed to read the output volume".to_owned()))
+    }
+
+    async fn set_volume(&mut self, volume: u32) {
+        let _ = self.output.send(Request::SetVolume(volume)).await;
+    }
+
+    async fn toggle_mute(&mut self) {
+        let _ = self.output.send(Request::ToggleMute).await;
+    }
+
+    async fn toggle_popup(&mut self) {
+        let _ = self.output.send(Request::TogglePopup).await;
+    }
+
+    async fn cycle_output(&mut self) {
+        let _ = self.output.send(Request::CycleOutput).await;
+    }
+}
+
+pub fn subscription() -> iced::Subscription<Request> {
+    Subscription::run_with_id(
+        BUS_NAME,
+        stream::channel(8, |output| async move {
+            // The connection serves requests for as long as it is alive
+            let _conn = match serve(output).await {
+                Ok(conn) => conn,
+                Err(err) => {
+                    tracing::error!("Failed to serve {BUS_NAME}: {err}");
+                    return futures::future::pending().await;
+                }
+            };
+            futures::future::pending().await
+        }),
+    )
+}
+
+async fn serve(output: Sender<Request>) -> zbus::Result<zbus::Connection> {
+    zbus::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, Control { output })?
+        .build()
+        .await
+}
@@ -0,0 +1,123 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The app id of the focused window, from the compositor's toplevel info.
+
+use cosmic::{
+    cctk::{
+        self,
+        cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1,
+        sctk::{
+            self,
+            registry::{ProvidesRegistryState, RegistryState},
+        },
+        toplevel_info::{ToplevelInfoHandler, ToplevelInfoState},
+        wayland_client::{Connection, QueueHandle, globals::registry_queue_init},
+        wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    },
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, StreamExt, channel::mpsc::UnboundedSender},
+};
+
+/// Emits the focused window's app id whenever another window takes focus, and `None`
+/// once the focused window closes. Focus moving to the panel or a popup keeps the last app.
+pub fn focused_app() -> iced::Subscription<Option<String>> {
+    Subscription::run_with_id(
+        "focused-app",
+        stream::channel(4, |mut output| async move {
+            let (tx, mut rx) = futures::channel::mpsc::unbounded();
+            // Wayland dispatch blocks, so it gets its own thread, which ends with the channel
+            std::thread::spawn(move || {
+                if let Err(err) = watch(tx) {
+                    tracing::warn!("Failed to follow the focused window: {err}");
+                }
+            });
+            while let Some(app_id) = rx.next().await {
+                if output.send(app_id).await.is_err() {
+                    break;
+                }
+            }
+            futures::future::pending().await
+        }),
+    )
+}
+
+fn watch(tx: UnboundedSender<Option<String>>) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut event_queue) = registry_queue_init::<State>(&conn)?;
+    let qh = event_queue.handle();
+    let registry_state = RegistryState::new(&globals);
+    let toplevel_info_state = ToplevelInfoState::try_new(&registry_state, &qh).ok_or("the compositor offers no toplevel info")?;
+    let mut state = State {
+        registry_state,
+        toplevel_info_state,
+        tx,
+        focused: None,
+        closed: false,
+    };
+    while !state.closed {
+        event_queue.blocking_dispatch(&mut state)?;
+    }
+    Ok(())
+}
+
+struct State {
+    registry_state: RegistryState,
+    toplevel_info_state: ToplevelInfoState,
+    tx: UnboundedSender<Option<String>>,
+    focused: Option<String>,
+    // Set once the subscription is gone
+    closed: bool,
+}
+
+impl State {
+    fn is_activated(&self, toplevel: &ExtForeignToplevelHandleV1) -> Option<&str> {
+        let info = self.toplevel_info_state.info(toplevel)?;
+        info.state
+            .contains(&zcosmic_toplevel_handle_v1::State::Activated)
+            .then_some(info.app_id.as_str())
+            .filter(|app_id| !app_id.is_empty())
+    }
+
+    fn send(&mut self, app_id: Option<String>) {
+        if app_id != self.focused {
+            self.focused = app_id.clone();
+            self.closed = self.tx.unbounded_send(app_id).is_err();
+        }
+    }
+}
+
+impl ToplevelInfoHandler for State {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, toplevel: &ExtForeignToplevelHandleV1) {
+        if let Some(app_id) = self.is_activated(toplevel).map(str::to_owned) {
+            self.send(Some(app_id));
+        }
+    }
+
+    fn update_toplevel(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, toplevel: &ExtForeignToplevelHandleV1) {
+        if let Some(app_id) = self.is_activated(toplevel).map(str::to_owned) {
+            self.send(Some(app_id));
+        }
+    }
+
+    fn toplevel_closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, toplevel: &ExtForeignToplevelHandleV1) {
+        if self.is_activated(toplevel).is_some() {
+            self.send(None);
+        }
+    }
+}
+
+impl ProvidesRegistryState for State {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!();
+}
+
+cctk::delegate_toplevel_info!(State);
+sctk::delegate_registry!(State);
@@ -1,22 +1,35 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+mod accent;
+mod anim;
+mod art;
+mod audio_command;
+mod bluetooth;
 mod config;
+mod dbus_control;
+mod focus;
 mod localize;
 mod mouse_area;
 mod mpris_subscription;
+mod notify;
+mod peak;
+mod session;
+mod upower;
+mod wpctl;
 
-use crate::localize::localize;
-use config::{amplification_sink, amplification_source, AudioAppletConfig};
+use crate::localize::{self, localize};
+use config::{amplification_sink, amplification_source, ActiveDeviceClick, AmplificationConfig, AudioAppletConfig, ClickModifier, DeviceSort, MediaLayout, MeterLayout, MuteMode, PanelAction, PrimarySlider, ScrollCurve};
 use cosmic::{
     applet::{menu_button, padded_control},
+    cosmic_config::{self, CosmicConfigEntry},
     cctk::sctk::reexports::calloop,
     cosmic_theme::Spacing,
     iced::{
         self,
         futures::StreamExt,
         platform_specific::shell::wayland::commands::popup::{destroy_popup, get_popup},
-        widget::{column, row, slider, image},
+        widget::{column, row, slider, image, progress_bar},
         window, Alignment, Length,
     },
     theme,
@@ -26,16 +39,96 @@ use cosmic::{
 use cosmic::iced::Renderer;
 
 use cosmic_settings_sound_subscription as css;
-use cosmic_time::{Instant, Timeline};
+use cosmic_time::Instant;
 use mpris_subscription::{MprisRequest, MprisUpdate};
-use mpris2_zbus::player::PlaybackStatus;
+use audio_command::{AudioCommand, QueueEvent, Target};
+use mpris2_zbus::{media_player::MediaPlayer, player::{LoopStatus, PlaybackStatus}};
 use std::process::Command;
+use std::sync::LazyLock;
+use std::time::Duration;
 
 // Icons
 const GO_BACK: &str = "media-skip-backward-symbolic";
 const GO_NEXT: &str = "media-skip-forward-symbolic";
 const PAUSE: &str = "media-playback-pause-symbolic";
 const PLAY: &str = "media-playback-start-symbolic";
+const GO_BACK_RTL: &str = "media-skip-backward-rtl-symbolic";
+const GO_NEXT_RTL: &str = "media-skip-forward-rtl-symbolic";
+const PLAY_RTL: &str = "media-playback-start-rtl-symbolic";
+const STOP: &str = "media-playback-stop-symbolic";
+const REFRESH: &str = "view-refresh-symbolic";
+const SETTINGS: &str = "preferences-system-symbolic";
+const SHUFFLE: &str = "media-playlist-shuffle-symbolic";
+const REPEAT: &str = "media-playlist-repeat-symbolic";
+const REPEAT_SONG: &str = "media-playlist-repeat-song-symbolic";
+const BT_CONNECTED: &str = "bluetooth-active-symbolic";
+const BT_DISCONNECTED: &str = "bluetooth-disconnected-symbolic";
+const AUDIO_GENERIC: &str = "audio-x-generic-symbolic";
+
+const REVEAL_DURATION: Duration = Duration::from_millis(200);
+const VOLUME_ANIM_DURATION: Duration = Duration::from_millis(150);
+const HOVER_OPEN_DELAY: Duration = Duration::from_millis(300);
+// Grace period for moving the cursor between the panel icon and the popup
+const HOVER_CLOSE_DELAY: Duration = Duration::from_millis(400);
+// How long the refresh button stays disabled after a rescan
+const REFRESH_COOLDOWN: Duration = Duration::from_secs(1);
+// Backoff for restarting the device subscription after PipeWire goes away
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+// How long to wait for the first devices before reporting that there are none
+const DEVICE_DETECT_TIMEOUT: Duration = Duration::from_secs(3);
+// Fade of the highlight on a default device changed by another application
+const PULSE_DURATION: Duration = Duration::from_millis(1200);
+// How long a transient notice such as "Copied" stays
+const NOTICE_TIMEOUT: Duration = Duration::from_secs(2);
+// How long a plugged in or removed device's row takes to slide in or out
+const HOTPLUG_DURATION: Duration = Duration::from_millis(250);
+// How long a sink change may go unconfirmed before PipeWire is queried
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(1);
+// How long a player may take to confirm a play, pause or mode change before it is shown as it was
+const PLAYER_CONFIRM_TIMEOUT: Duration = Duration::from_secs(2);
+// Peak level counted as clipping, just under full scale, and how long the warning outlasts it
+const CLIP_LEVEL: f32 = 0.995;
+const CLIP_WINDOW: Duration = Duration::from_millis(1500);
+// Slider travel per volume point while dragging with Shift held
+const FINE_DRAG_DIVISOR: i32 = 4;
+// How long a pressed media button stays highlighted
+const PRESS_FLASH: Duration = Duration::from_millis(200);
+// How long a new track's title takes to fade from the accent color
+const TRACK_FLASH: Duration = Duration::from_millis(800);
+// Playback speeds offered, filtered to what the player supports
+const RATES: [f64; 5] = [0.75, 1.0, 1.25, 1.5, 2.0];
+// Quiet time before announcing a new default output, so enumeration bursts notify once
+const NOTIFY_DEBOUNCE: Duration = Duration::from_secs(1);
+// Quiet time after a sink or stream change before reading them again, so a drag reads once
+const SINK_CHANGE_DEBOUNCE: Duration = Duration::from_millis(250);
+// How long the undo prompt stays after switching devices
+const UNDO_TIMEOUT: Duration = Duration::from_secs(5);
+// A new model volume this far from a pending drag value means the drag is stale
+const STALE_DRAG_THRESHOLD: u32 = 5;
+// Approximate height of one device row, used to size the revealer while it animates
+const DEVICE_ROW_HEIGHT: f32 = 36.0;
+const COMPACT_ART_SIZE: u16 = 48;
+// Recently used devices kept for `DeviceSort::LastUsed`
+const MAX_RECENT_DEVICES: usize = 16;
+// The width the album art margin was designed for
+const NATURAL_POPUP_WIDTH: f32 = 360.0;
+const MIN_POPUP_WIDTH: u16 = 240;
+// Approximate width of a 24px icon button, for aligning content under the sliders
+const MUTE_BUTTON_WIDTH: f32 = 40.0;
+// ...and of the 16px -/+ buttons beside the sliders
+const STEP_BUTTON_WIDTH: f32 = 32.0;
+// Character budgets for title/artist, keeping long names from widening the popup
+const COMPACT_TEXT_CHARS: usize = 24;
+const MEDIA_TEXT_CHARS: usize = 40;
+const PANEL_TITLE_CHARS: usize = 20;
+const MARQUEE_STEP: Duration = Duration::from_millis(200);
+// Steps the marquee rests at either end before reversing
+const MARQUEE_PAUSE_STEPS: usize = 8;
+
+static SINK_ENTRY_ID: LazyLock<cosmic::widget::Id> = LazyLock::new(|| cosmic::widget::Id::new("sink-volume-entry"));
+static LABEL_ENTRY_ID: LazyLock<cosmic::widget::Id> = LazyLock::new(|| cosmic::widget::Id::new("device-label-entry"));
+static SOURCE_ENTRY_ID: LazyLock<cosmic::widget::Id> = LazyLock::new(|| cosmic::widget::Id::new("source-volume-entry"));
 
 pub fn run() -> cosmic::iced::Result {
     localize();
@@ -50,37 +143,221 @@ pub struct Audio {
     is_open: IsOpen,
     max_sink_volume: u32,
     max_source_volume: u32,
-    sink_breakpoints: &'static [u32],
-    source_breakpoints: &'static [u32],
-    timeline: Timeline,
+    sink_breakpoints: Vec<u32>,
+    source_breakpoints: Vec<u32>,
     config: AudioAppletConfig,
     player_status: Option<mpris_subscription::PlayerStatus>,
+    // The player's own state while `player_status` shows a request's expected result,
+    // restored if the player never confirms it
+    reported_player: Option<(mpris_subscription::PlayerStatus, anim::Progress)>,
     token_tx: Option<calloop::channel::Sender<cosmic::applet::token::subscription::TokenRequest>>,
+    command_queue: Option<audio_command::Queue>,
+    // Whether a stream plays through the default sink, polled only for the indicator
+    sink_running: bool,
+    // Rate and format of the default sink, read when the popup opens or the sink changes
+    sink_format: Option<String>,
     
     // SAFE DRAG STATES
     sink_drag_val: Option<u32>,
     source_drag_val: Option<u32>,
+    // Text typed into the exact-volume fields, while they are being edited
+    sink_entry: Option<String>,
+    source_entry: Option<String>,
     last_update: Option<Instant>,
+    // Kept apart so dragging a device list slider doesn't hold back the main ones
+    device_last_update: Option<Instant>,
+
+    reveal_anim: Option<RevealAnim>,
+    // Slider transitions for volume changes that did not come from a drag
+    sink_anim: Option<anim::Tween>,
+    source_anim: Option<anim::Tween>,
+    // When the current title started scrolling
+    marquee_start: Option<Instant>,
+    // Bumped to restart the device subscription, which re-enumerates from scratch
+    device_generation: u32,
+    // Failed reconnects since the device subscription ended; `None` while connected
+    reconnect_attempts: Option<u32>,
+    // Running until the device subscription reports devices or times out
+    detecting: Option<anim::Progress>,
+    refresh_cooldown: Option<anim::Progress>,
+    // Mirror the popup for right-to-left locales
+    rtl: bool,
+    hover: Hover,
+    view_mode: ViewMode,
+    bt_devices: Vec<bluetooth::BtDevice>,
+    // Module index of the combined sink this applet loaded, to unload only that one
+    combined_module: Option<u32>,
+    // A device being connected or created, to make default once its sink appears
+    pending_default: Option<PendingDefault>,
+    // An output's remembered volume, applied once it has become the default
+    pending_volume_restore: Option<(String, u32)>,
+    batteries: Vec<upower::Battery>,
+    // Bluetooth addresses of the sinks and sources of Bluetooth devices, by name
+    bt_addresses: std::collections::HashMap<String, String>,
+    // PipeWire ids and levels of all sinks, for adjusting those that aren't the default
+    sink_nodes: Vec<wpctl::Node>,
+    // App id of the focused window and the streams apps play, while `follow_focused_app` is set
+    focused_app: Option<String>,
+    app_streams: Vec<wpctl::AppStream>,
+    // Sources that are loopbacks of outputs, hidden unless `show_monitor_sources` is set
+    monitor_sources: Vec<String>,
+    // Outputs ticked for a combined sink; `None` outside of choosing them
+    combine: Option<Vec<String>>,
+    night_cap_active: bool,
+    // Output level to restore when unmuting in `MuteMode::ZeroVolume`
+    pre_mute_volume: Option<u32>,
+    // Whether the input was muted before the session went idle or locked, while away
+    muted_for_away: Option<bool>,
+    // Level and slider position where a Shift-drag started, scaled from there on
+    fine_anchor: Option<(DeviceKind, i32, i32)>,
+    // Device being renamed and the label typed so far
+    label_entry: Option<(String, String)>,
+    // Runs after an optimistic output change; PipeWire is then asked what it really has
+    sink_confirm: Option<anim::Progress>,
+    // An output level above 100% held back until confirmed, and whether it was this session
+    boost_prompt: Option<u32>,
+    boost_confirmed: bool,
+    // Input level to restore when turning the microphone boost off
+    pre_boost_volume: Option<u32>,
+    undo: Option<UndoSwitch>,
+    // Defaults this applet asked for, so only changes made elsewhere are highlighted
+    requested_sink: Option<String>,
+    requested_source: Option<String>,
+    default_pulse: Option<(DeviceKind, anim::Progress)>,
+    hotplug: Vec<Hotplug>,
+    track_flash: Option<anim::Progress>,
+    // A short confirmation shown above the footer
+    notice: Option<(String, anim::Progress)>,
+    // Running while the output recently reached full scale
+    clipping: Option<anim::Progress>,
+    // Channel count of the default source and its latest peaks, for the input meter
+    input_channels: Option<u8>,
+    input_peaks: Vec<f32>,
+    // Output to announce once `NOTIFY_DEBOUNCE` passes without another change
+    pending_notification: Option<(String, anim::Progress)>,
+    // Set from the first sink change until the `SINK_CHANGE_DEBOUNCE` read
+    sink_changes: Option<anim::Progress>,
+    // Id of the last device notification, replaced rather than stacked
+    notification_id: u32,
+    // Album art resampled for the current layout and scale, with its width in pixels;
+    // `None` when it couldn't be decoded, so it isn't tried again
+    scaled_art: Option<(std::path::PathBuf, u32, Option<cosmic::widget::image::Handle>)>,
+    // Album art and the accent color picked from it; `None` when it has no clear color
+    art_accent: Option<(std::path::PathBuf, Option<iced::Color>)>,
+    // The media request last pressed, highlighted briefly as feedback
+    pressed_media: Option<(std::mem::Discriminant<MprisRequest>, anim::Progress)>,
+    // Held modifiers, for `settings_modifier` clicks on the panel icon
+    modifiers: iced::keyboard::Modifiers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeviceKind { Sink, Source }
+
+/// A device row sliding in after the device appeared, or out after it disappeared.
+#[derive(Debug, Clone)]
+struct Hotplug {
+    kind: DeviceKind,
+    name: String,
+    /// Where the row of a removed device was, to keep showing it there.
+    removed_at: Option<usize>,
+    progress: anim::Progress,
+}
+
+/// An output to make default once its sink appears.
+#[derive(Debug, Clone)]
+enum PendingDefault {
+    Named(String),
+    /// The sink of the Bluetooth device with this address.
+    Bluetooth(String),
+}
+
+/// The device that was default before the most recent switch.
+#[derive(Debug, Clone)]
+struct UndoSwitch {
+    kind: DeviceKind,
+    previous: String,
+    current: String,
+    expires: anim::Progress,
+}
+
+/// Expiry timers that only change state once they run out, so they sleep instead
+/// of keeping the frame tick running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Timer {
+    RefreshCooldown,
+    Undo,
+    Detecting,
+    Notice,
+    PressedMedia,
+    SinkConfirm,
+    PlayerConfirm,
+    Clipping,
+    Notification,
+    SinkChanges,
+    HoverOpen,
+    HoverClose,
+}
+
+/// Which page the popup shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ViewMode { #[default] Main, Outputs, Inputs }
+
+/// Cursor tracking for `open_on_hover`, across the panel icon and the popup.
+#[derive(Debug, Default)]
+struct Hover {
+    icon: bool,
+    popup: bool,
+    open_timer: Option<anim::Progress>,
+    close_timer: Option<anim::Progress>,
 }
 
-#[derive(Debug, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum IsOpen { #[default] None, Output, Input }
 
+/// A revealer transition: `opening` expands while `closing` collapses.
+#[derive(Debug, Clone, Copy)]
+struct RevealAnim {
+    opening: IsOpen,
+    closing: IsOpen,
+    progress: anim::Progress,
+}
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Ignore,
     SetSinkVolume(u32), DragSink(u32), CommitSink, ToggleSinkMute,
-    SetSourceVolume(u32), DragSource(u32), CommitSource, ToggleSourceMute,
-    SetDefaultSink(usize), SetDefaultSource(usize), OutputToggle, InputToggle,
-    TogglePopup,
+    SetSourceVolume(u32), DragSource(u32), CommitSource, ToggleSourceMute, ToggleMicBoost, ConfirmBoost, CancelBoost,
+    EditSinkVolume(String), SetSinkVolumeExact(String), EditSourceVolume(String), SetSourceVolumeExact(String),
+    SetDefaultSink(String), SetDefaultSource(String), CycleSink, EditDeviceLabel(String, String), SetDeviceLabel(String), UndoDeviceSwitch, OutputToggle, InputToggle, ShowMain,
+    TogglePopup, PanelPress, PanelDoubleTap, Modifiers(iced::keyboard::Modifiers),
+    HoverIcon(bool), HoverPopup(bool),
     CloseRequested(window::Id),
-    ConfigChanged(AudioAppletConfig),
-    Mpris(MprisUpdate), MprisRequest(MprisRequest),
+    ConfigChanged(AudioAppletConfig), AmplificationChanged,
+    Mpris(MprisUpdate), MprisRequest(MprisRequest), CopyTrackInfo, SinkState(Option<(u32, bool)>), ArtScaled(std::path::PathBuf, u32, Option<cosmic::widget::image::Handle>), ArtAccent(std::path::PathBuf, Option<iced::Color>), DbusControl(dbus_control::Request), Notified(u32), SessionAway(bool), CommandQueue(QueueEvent), OutputPeaks(Vec<f32>), InputChannels(Option<u8>), InputPeaks(Vec<f32>), ToggleMeterChannel(u8), ToggleMeterLayout,
     OpenSettings,
+    RefreshDevices,
+    DevicesLost, Reconnect,
+    BluetoothDevices(Vec<bluetooth::BtDevice>),
+    ConnectBluetooth(String),
+    BluetoothConnected(bool), BluetoothAddresses(std::collections::HashMap<String, String>),
+    Batteries(Vec<upower::Battery>),
+    SinkNodes(Vec<wpctl::Node>), FocusedApp(Option<String>), AppStreams(Vec<wpctl::AppStream>), MonitorSources(Vec<String>), SetDeviceVolume(String, u32), CommitDeviceVolume(String),
+    ChooseCombined, ToggleCombined(String), CreateCombinedSink(Vec<String>), CombinedSinkCreated(Option<u32>),
+    CheckNightCap, LocalHour(Option<u8>), CheckSinkRunning, SinkChanged, SinkRunning(bool), SinkFormat(Option<String>), DismissScrollTip,
     Subscription(css::Message),
     Frame(Instant),
+    TimerDone(Timer),
+    Key(window::Id, KeyNav),
 }
 
+/// The device list a Sound settings button sits under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundPage { Output, Input }
+
+/// Popup keys that no focused widget handled.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyNav { FocusNext, FocusPrevious, VolumeUp, VolumeDown, SeekBackward, SeekForward, PlayPause, Back }
+
 impl cosmic::Application for Audio {
     type Message = Message;
     type Executor = cosmic::SingleThreadExecutor;
@@ -92,192 +369,1804 @@ impl cosmic::Application for Audio {
             Self {
                 core,
                 model: css::Model::default(),
+                rtl: localize::is_rtl(),
+                detecting: Some(anim::Progress::new(DEVICE_DETECT_TIMEOUT)),
                 ..Default::default()
             },
-            Task::none(),
+            after(DEVICE_DETECT_TIMEOUT, Timer::Detecting),
         )
     }
 
     fn core(&self) -> &cosmic::app::Core { &self.core }
     fn core_mut(&mut self) -> &mut cosmic::app::Core { &mut self.core }
     fn style(&self) -> Option<cosmic::iced_runtime::Appearance> { Some(cosmic::applet::style()) }
+    fn on_close_requested(&self, id: window::Id) -> Option<Message> { Some(Message::CloseRequested(id)) }
 
     fn update(&mut self, message: Message) -> Task<cosmic::Action<Self::Message>> {
         match message {
-            Message::Frame(now) => self.timeline.now(now),
+            Message::Frame(now) => {
+                if self.reveal_anim.is_some_and(|a| a.progress.is_done(now)) { self.reveal_anim = None; }
+                if self.sink_anim.is_some_and(|a| a.is_done(now)) { self.sink_anim = None; }
+                if self.source_anim.is_some_and(|a| a.is_done(now)) { self.source_anim = None; }
+                if self.default_pulse.is_some_and(|(_, p)| p.is_done(now)) { self.default_pulse = None; }
+                self.hotplug.retain(|h| !h.progress.is_done(now));
+                if self.track_flash.is_some_and(|p| p.is_done(now)) { self.track_flash = None; }
+            }
+            // A timer re-armed since this sleep started is left for its own sleep
+            Message::TimerDone(timer) => {
+                let now = Instant::now();
+                match timer {
+                    Timer::RefreshCooldown => if self.refresh_cooldown.is_some_and(|p| p.is_done(now)) { self.refresh_cooldown = None; },
+                    Timer::Undo => if self.undo.as_ref().is_some_and(|u| u.expires.is_done(now)) { self.undo = None; },
+                    Timer::Detecting => if self.detecting.is_some_and(|p| p.is_done(now)) { self.detecting = None; },
+                    Timer::Notice => if self.notice.as_ref().is_some_and(|(_, p)| p.is_done(now)) { self.notice = None; },
+                    Timer::Clipping => if self.clipping.is_some_and(|p| p.is_done(now)) { self.clipping = None; },
+                    Timer::PressedMedia => if self.pressed_media.is_some_and(|(_, p)| p.is_done(now)) { self.pressed_media = None; },
+                    Timer::SinkConfirm => if self.sink_confirm.take_if(|p| p.is_done(now)).is_some() {
+                        return Task::perform(wpctl::default_sink_state(), |state| cosmic::Action::from(Message::SinkState(state)));
+                    },
+                    // Unchanged state is never re-sent, so a player that ignored the request stays silent
+                    Timer::PlayerConfirm => if let Some((reported, _)) = self.reported_player.take_if(|(_, p)| p.is_done(now)) {
+                        self.player_status = Some(reported);
+                    },
+                    Timer::Notification => if let Some((name, _)) = self.pending_notification.take_if(|(_, p)| p.is_done(now)) {
+                        let show = notify::show(fl!("sound"), fl!("output-switched", device = name), self.output_icon_name(), self.notification_id);
+                        return Task::perform(show, |id| cosmic::Action::from(Message::Notified(id)));
+                    },
+                    Timer::SinkChanges => if self.sink_changes.take_if(|p| p.is_done(now)).is_some() {
+                        return self.reread_sinks();
+                    },
+                    Timer::HoverOpen => if self.hover.open_timer.take_if(|t| t.is_done(now)).is_some() && self.hover.icon && self.popup.is_none() {
+                        return self.update(Message::TogglePopup);
+                    },
+                    Timer::HoverClose => if self.hover.close_timer.take_if(|t| t.is_done(now)).is_some() && !self.hover.icon && !self.hover.popup && self.popup.is_some() {
+                        return self.update(Message::TogglePopup);
+                    },
+                }
+            }
+            // Focus follows the widget tree, so Tab walks the popup top to bottom; focused buttons activate on Enter/Space
+            // The panel surface and other popups get key events too; only the popup's count
+            Message::Key(id, _) if self.popup != Some(id) => {}
+            Message::Key(_, KeyNav::FocusNext) => return iced::widget::focus_next(),
+            Message::Key(_, KeyNav::FocusPrevious) => return iced::widget::focus_previous(),
+            // The sliders cannot take focus, so the arrow keys drive the output level directly
+            Message::Key(_, KeyNav::VolumeUp) => return self.update(Message::SetSinkVolume(self.stepped_volume(self.main_sink().0, 1.0, self.capped(100)))),
+            Message::Key(_, KeyNav::VolumeDown) => return self.update(Message::SetSinkVolume(self.stepped_volume(self.main_sink().0, -1.0, self.main_sink().0))),
+            Message::Key(_, nav @ (KeyNav::SeekBackward | KeyNav::SeekForward)) => {
+                let Some(s) = self.player_status.as_ref().filter(|s| s.can_seek) else { return Task::none(); };
+                let Some(pos) = s.position_now() else { return Task::none(); };
+                let step = Duration::from_secs(self.config.seek_step.into());
+                let target = match nav {
+                    KeyNav::SeekForward => s.length.map_or(pos + step, |length| (pos + step).min(length)),
+                    _ => pos.saturating_sub(step),
+                };
+                return self.update(Message::MprisRequest(MprisRequest::SetPosition(target)));
+            }
+            Message::Key(_, KeyNav::PlayPause) => {
+                let Some(s) = self.player_status.as_ref() else { return Task::none(); };
+                let req = match s.status {
+                    PlaybackStatus::Playing => s.can_pause.then_some(MprisRequest::Pause),
+                    _ => s.can_play.then_some(MprisRequest::Play),
+                };
+                if let Some(req) = req { return self.update(Message::MprisRequest(req)); }
+            }
+            Message::Key(_, KeyNav::Back) => match self.view_mode {
+                ViewMode::Main => return self.close_popup(),
+                _ => self.view_mode = ViewMode::Main,
+            },
+            Message::HoverIcon(_) | Message::HoverPopup(_) if !self.config.open_on_hover => {}
+            Message::HoverIcon(over) => {
+                self.hover.icon = over;
+                self.hover.open_timer = over.then(|| anim::Progress::new(HOVER_OPEN_DELAY));
+                self.hover.close_timer = (!over).then(|| anim::Progress::new(HOVER_CLOSE_DELAY));
+                return if over { after(HOVER_OPEN_DELAY, Timer::HoverOpen) } else { after(HOVER_CLOSE_DELAY, Timer::HoverClose) };
+            }
+            Message::HoverPopup(over) => {
+                self.hover.popup = over;
+                self.hover.close_timer = (!over).then(|| anim::Progress::new(HOVER_CLOSE_DELAY));
+                if !over { return after(HOVER_CLOSE_DELAY, Timer::HoverClose); }
+            }
             
             // --- SAFE AUDIO VOLUME (WPCTL) ---
-            Message::DragSink(val) => { self.sink_drag_val = Some(val); self.model.sink_volume_text = format!("{}%", val); }
-            Message::DragSource(val) => { self.source_drag_val = Some(val); self.model.source_volume_text = format!("{}%", val); }
+            Message::DragSink(val) => { let val = self.fine_drag(DeviceKind::Sink, val); let val = self.guard_boost(self.capped(val)); self.sink_anim = None; self.sink_drag_val = Some(val); self.model.sink_volume_text = localize::percent(val); }
+            Message::DragSource(val) => { let val = self.fine_drag(DeviceKind::Source, val); self.source_anim = None; self.source_drag_val = Some(val); self.model.source_volume_text = localize::percent(val); }
             
             Message::CommitSink => {
+                self.fine_anchor = None;
                 if let Some(val) = self.sink_drag_val.take() {
-                    let _ = Command::new("wpctl").args(["set-volume", "@DEFAULT_AUDIO_SINK@", &format!("{:.2}", val as f32 / 100.0)]).spawn();
+                    let val = self.capped(val);
+                    self.apply_sink_volume(val);
+                    let confirm = self.expect_sink(val, self.main_sink().1);
+                    self.remember_sink_volume(val);
+                    return confirm;
                 }
             }
             Message::CommitSource => {
+                self.fine_anchor = None;
                 if let Some(val) = self.source_drag_val.take() {
-                    let _ = Command::new("wpctl").args(["set-volume", "@DEFAULT_AUDIO_SOURCE@", &format!("{:.2}", val as f32 / 100.0)]).spawn();
+                    self.pre_boost_volume = None;
+                    self.run_command(AudioCommand::SetVolume(Target::DefaultSource, val));
                 }
             }
             Message::SetSinkVolume(val) => {
+                // Throttled first, so a dropped step can't raise the boost prompt
                 if let Some(last) = self.last_update { if last.elapsed().as_millis() < 50 { return Task::none(); } }
                 self.last_update = Some(Instant::now());
-                let _ = Command::new("wpctl").args(["set-volume", "@DEFAULT_AUDIO_SINK@", &format!("{:.2}", val as f32 / 100.0)]).spawn();
+                let val = self.guard_boost(self.capped(val));
+                self.apply_sink_volume(val);
+                // Raising the level of a muted sink would otherwise stay silent
+                let unmute = self.config.unmute_on_scroll && self.main_sink().1 && val > 0;
+                if unmute {
+                    self.run_command(AudioCommand::SetMute(self.sink_target(), false));
+                }
+                self.expect_stream(val, self.main_sink().1 && !unmute);
+            }
+            Message::SetSourceVolume(val) => {
+                if let Some(last) = self.last_update { if last.elapsed().as_millis() < 50 { return Task::none(); } }
+                self.last_update = Some(Instant::now());
+                self.pre_boost_volume = None;
+                self.run_command(AudioCommand::SetVolume(Target::DefaultSource, val));
             }
             
-            Message::ToggleSinkMute => { let _ = Command::new("wpctl").args(["set-mute", "@DEFAULT_AUDIO_SINK@", "toggle"]).spawn(); }
-            Message::ToggleSourceMute => { let _ = Command::new("wpctl").args(["set-mute", "@DEFAULT_AUDIO_SOURCE@", "toggle"]).spawn(); }
+            Message::ConfirmBoost => {
+                self.boost_confirmed = true;
+                if let Some(val) = self.boost_prompt.take() { return self.update(Message::SetSinkVolume(val)); }
+            }
+            Message::CancelBoost => self.boost_prompt = None,
+            Message::ToggleSinkMute => match self.config.mute_mode {
+                MuteMode::Flag => {
+                    let (volume, muted) = self.main_sink();
+                    self.run_command(AudioCommand::ToggleMute(self.sink_target()));
+                    return self.expect_sink(volume, !muted);
+                }
+                MuteMode::ZeroVolume => {
+                    let (volume, muted) = self.main_sink();
+                    let val = match self.pre_mute_volume.take() {
+                        Some(previous) => self.capped(previous),
+                        None if volume > 0 => { self.pre_mute_volume = Some(volume); 0 }
+                        None => return Task::none(),
+                    };
+                    self.apply_sink_volume(val);
+                    return self.expect_sink(val, muted);
+                }
+            },
+            // The first edit swaps the label for a field and focuses it
+            Message::EditSinkVolume(s) => if self.sink_entry.replace(s).is_none() {
+                return cosmic::widget::text_input::focus(SINK_ENTRY_ID.clone());
+            },
+            Message::EditSourceVolume(s) => if self.source_entry.replace(s).is_none() {
+                return cosmic::widget::text_input::focus(SOURCE_ENTRY_ID.clone());
+            },
+            // Invalid input just closes the field, reverting to the current level
+            Message::SetSinkVolumeExact(s) => if self.sink_entry.take().is_some() {
+                if let Some(val) = parse_volume(&s) {
+                    let val = self.guard_boost(self.capped(val.min(self.max_sink_volume)));
+                    self.apply_sink_volume(val);
+                    self.expect_stream(val, self.main_sink().1);
+                }
+            },
+            Message::SetSourceVolumeExact(s) => if self.source_entry.take().is_some() {
+                if let Some(val) = parse_volume(&s) {
+                    let val = val.min(self.max_source_volume);
+                    self.pre_boost_volume = None;
+                    self.run_command(AudioCommand::SetVolume(Target::DefaultSource, val));
+                }
+            },
+            Message::ToggleSourceMute => { self.run_command(AudioCommand::ToggleMute(Target::DefaultSource)); }
+            // Without amplification the boost tops out at 100%
+            Message::ToggleMicBoost => {
+                let val = match self.pre_boost_volume.take() {
+                    Some(previous) => previous.min(self.max_source_volume),
+                    None => { self.pre_boost_volume = Some(self.model.source_volume); self.config.mic_boost_level.min(self.max_source_volume) }
+                };
+                self.run_command(AudioCommand::SetVolume(Target::DefaultSource, val));
+            }
             
-            Message::SetDefaultSink(idx) => return self.model.set_default_sink(idx).map(|m| cosmic::Action::from(Message::Subscription(m))),
-            Message::SetDefaultSource(idx) => return self.model.set_default_source(idx).map(|m| cosmic::Action::from(Message::Subscription(m))),
+            // The first edit of a device opens its rename field and focuses it
+            Message::EditDeviceLabel(name, text) => {
+                let opening = self.label_entry.as_ref().is_none_or(|(n, _)| *n != name);
+                self.label_entry = Some((name, text));
+                if opening { return cosmic::widget::text_input::focus(LABEL_ENTRY_ID.clone()); }
+            }
+            // An empty label goes back to the device's own name
+            Message::SetDeviceLabel(text) => if let Some((name, _)) = self.label_entry.take() {
+                let mut labels = self.config.device_labels.clone();
+                match text.trim() {
+                    "" => { labels.remove(&name); }
+                    label => { labels.insert(name, label.to_owned()); }
+                }
+                if labels != self.config.device_labels {
+                    let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+                        .and_then(|config| self.config.set_device_labels(&config, labels));
+                    if let Err(err) = result { tracing::error!("Failed to save device label: {err:?}"); }
+                }
+            },
+            // Resolve the name at click time; the list may have changed since it was rendered
+            // Re-selecting the default would be a no-op round trip to PipeWire
+            Message::SetDefaultSink(name) if self.active_sink_name() == Some(&name) => self.close_device_list(IsOpen::Output),
+            Message::SetDefaultSource(name) if self.active_source_name() == Some(&name) => self.close_device_list(IsOpen::Input),
+            // Only a switch that went through is remembered for undo
+            Message::SetDefaultSink(name) => {
+                let previous = self.active_sink_name().cloned();
+                let Some(task) = self.select_sink(&name) else { return Task::none() };
+                self.remember_recent(&name);
+                self.pending_volume_restore = self.config.device_volumes.get(&name).map(|v| (name.clone(), *v));
+                // The combined sink this applet made only lasts while it is the output, so there is nothing to undo to
+                if let Some(module) = self.combined_module.take_if(|_| previous.as_deref() == Some(wpctl::COMBINED_SINK)) {
+                    let unload = Task::perform(wpctl::unload_module(module), |_| cosmic::Action::from(Message::Ignore));
+                    return self.then_close_if_configured(task.chain(unload));
+                }
+                let undo = self.remember_switch(DeviceKind::Sink, previous, &name);
+                return self.then_close_if_configured(Task::batch([task, undo]));
+            }
+            Message::SetDefaultSource(name) => {
+                let previous = self.active_source_name().cloned();
+                let Some(task) = self.select_source(&name) else { return Task::none() };
+                self.remember_recent(&name);
+                let undo = self.remember_switch(DeviceKind::Source, previous, &name);
+                return self.then_close_if_configured(Task::batch([task, undo]));
+            }
+            // Next output in list order, wrapping around; a single output stays put
+            Message::CycleSink => {
+                // The model's order, which selecting doesn't reshuffle the way `LastUsed` does
+                let sinks = self.model.sinks();
+                if sinks.len() < 2 { return Task::none(); }
+                let next = match self.active_sink_name().and_then(|a| sinks.iter().position(|n| n == a)) {
+                    Some(i) => sinks.get((i + 1) % sinks.len()),
+                    None => sinks.first(),
+                };
+                if let Some(next) = next.cloned() { return self.update(Message::SetDefaultSink(next)); }
+            }
+            Message::UndoDeviceSwitch => if let Some(undo) = self.undo.take() {
+                let task = match undo.kind {
+                    DeviceKind::Sink => self.select_sink(&undo.previous),
+                    DeviceKind::Source => self.select_source(&undo.previous),
+                };
+                return task.unwrap_or_else(Task::none);
+            },
 
             // --- MEDIA CONTROL ---
             Message::MprisRequest(req) => {
-                match req {
-                    MprisRequest::Play => { let _ = Command::new("playerctl").arg("play").spawn(); },
-                    MprisRequest::Pause => { let _ = Command::new("playerctl").arg("pause").spawn(); },
-                    MprisRequest::Next => { let _ = Command::new("playerctl").arg("next").spawn(); },
-                    MprisRequest::Previous => { let _ = Command::new("playerctl").arg("previous").spawn(); },
-                    MprisRequest::Raise => {},
-                }
+                self.pressed_media = Some((std::mem::discriminant(&req), anim::Progress::new(PRESS_FLASH)));
+                let mut flash = after(PRESS_FLASH, Timer::PressedMedia);
+                let optimistic = matches!(req, MprisRequest::Play | MprisRequest::Pause | MprisRequest::Stop
+                    | MprisRequest::SetShuffle(_) | MprisRequest::SetLoopStatus(_) | MprisRequest::SetRate(_));
+                if optimistic { flash = Task::batch([flash, self.expect_player()]); }
+                let Some(s) = self.player_status.as_mut() else { return flash };
+                // Goes to the shown player itself, not whichever one a tool would pick
+                let player = s.player.clone();
+                let call = match req {
+                    // Show the new state right away; the player's next update replaces it, or `expect_player` restores the old one
+                    MprisRequest::Play => { self.set_playback(PlaybackStatus::Playing); media_call("play", async move { player.play().await }) },
+                    MprisRequest::Pause => { self.set_playback(PlaybackStatus::Paused); media_call("pause", async move { player.pause().await }) },
+                    MprisRequest::Stop => { self.set_playback(PlaybackStatus::Stopped); media_call("stop", async move { player.stop().await }) },
+                    MprisRequest::Next => media_call("skip to the next track", async move { player.next().await }),
+                    MprisRequest::Previous => media_call("go to the previous track", async move { player.previous().await }),
+                    MprisRequest::Raise => {
+                        let conn = player.inner().connection().clone();
+                        let name = player.inner().destination().to_owned();
+                        media_call("raise the player", async move { MediaPlayer::new(&conn, name.into()).await?.raise().await })
+                    },
+                    // Reflect the new mode right away instead of waiting for the player's signal
+                    MprisRequest::SetShuffle(on) => {
+                        s.shuffle = Some(on);
+                        media_call("set shuffle", async move { player.set_shuffle(on).await })
+                    },
+                    MprisRequest::SetLoopStatus(status) => {
+                        s.loop_status = Some(status);
+                        media_call("set the loop status", async move { player.set_loop_status(status).await })
+                    },
+                    MprisRequest::SetRate(rate) => {
+                        s.rate = Some(rate);
+                        media_call("set the playback rate", async move { player.set_rate(rate).await })
+                    },
+                    // Players ignore positions without the current track's id
+                    MprisRequest::SetPosition(pos) => match s.track_id.clone() {
+                        Some(track_id) => media_call("seek", async move { player.set_position(&track_id, pos).await }),
+                        None => Task::none(),
+                    },
+                };
+                return Task::batch([flash, call]);
             }
 
+            // Updates since the change already show reality, so only a stale optimistic value differs
+            Message::SinkState(state) => if let Some(actual) = state {
+                let shown = (self.model.sink_volume, self.model.sink_mute);
+                if actual != shown {
+                    tracing::warn!("Output is at {actual:?}, not the {shown:?} shown");
+                    (self.model.sink_volume, self.model.sink_mute) = actual;
+                    self.model.sink_volume_text = localize::percent(actual.0);
+                    return self.show_notice(fl!("volume-change-failed"));
+                }
+            },
+            Message::Notified(id) => self.notification_id = id,
+            // Re-armed on every clipped block, so the warning stays for as long as clipping goes on
+            Message::OutputPeaks(peaks) => if peaks.iter().any(|p| *p >= CLIP_LEVEL) {
+                self.clipping = Some(anim::Progress::new(CLIP_WINDOW));
+                return after(CLIP_WINDOW, Timer::Clipping);
+            },
+            Message::CommandQueue(QueueEvent::Ready(queue)) => self.command_queue = Some(queue),
+            Message::CommandQueue(QueueEvent::Failed(command, err)) => {
+                tracing::warn!("{command:?}: {err}");
+                return self.show_notice(fl!("volume-change-failed"));
+            }
+            // Only undo a mute this applet made; an input muted beforehand stays muted
+            Message::SessionAway(true) => if self.config.auto_mute_mic_on_idle && self.muted_for_away.is_none() {
+                self.muted_for_away = Some(self.model.source_mute);
+                if !self.model.source_mute { self.run_command(AudioCommand::SetMute(Target::DefaultSource, true)); }
+            },
+            Message::SessionAway(false) => if self.muted_for_away.take() == Some(false) {
+                self.run_command(AudioCommand::SetMute(Target::DefaultSource, false));
+            },
+            // Scripts may set volumes back to back, so these skip the slider's throttle
+            Message::DbusControl(dbus_control::Request::SetVolume(val)) => self.apply_sink_volume(self.capped(val.min(self.max_sink_volume))),
+            Message::DbusControl(dbus_control::Request::ToggleMute) => return self.update(Message::ToggleSinkMute),
+            Message::DbusControl(dbus_control::Request::TogglePopup) => return self.update(Message::TogglePopup),
+            Message::DbusControl(dbus_control::Request::CycleOutput) => return self.update(Message::CycleSink),
+            Message::CopyTrackInfo => if let Some(info) = self.player_status.as_ref().and_then(track_info) {
+                return Task::batch([self.show_notice(fl!("copied")), iced::clipboard::write(info)]);
+            },
+            Message::Modifiers(modifiers) => self.modifiers = modifiers,
+            Message::PanelPress => {
+                let held = self.config.settings_modifier.is_some_and(|m| match m {
+                    ClickModifier::Ctrl => self.modifiers.control(),
+                    ClickModifier::Shift => self.modifiers.shift(),
+                    ClickModifier::Alt => self.modifiers.alt(),
+                    ClickModifier::Super => self.modifiers.logo(),
+                });
+                return self.update(match self.config.primary_action {
+                    _ if held => Message::OpenSettings,
+                    PanelAction::TogglePopup => Message::TogglePopup,
+                    PanelAction::ToggleMute => Message::ToggleSinkMute,
+                    PanelAction::OpenSettings => Message::OpenSettings,
+                });
+            }
+            // The first tap already went through as a press, so take back the popup it toggled
+            Message::PanelDoubleTap => match self.config.primary_action {
+                PanelAction::TogglePopup => {
+                    let undo = self.update(Message::TogglePopup);
+                    return Task::batch([undo, self.update(Message::ToggleSinkMute)]);
+                }
+                // That tap was the mute
+                PanelAction::ToggleMute => {}
+                PanelAction::OpenSettings => return self.update(Message::ToggleSinkMute),
+            },
             Message::OpenSettings => {
                 let _ = Command::new("cosmic-settings").arg("sound").spawn();
             }
+            Message::RefreshDevices => {
+                if self.refresh_cooldown.is_none() {
+                    self.model = css::Model::default();
+                    self.detecting = Some(anim::Progress::new(DEVICE_DETECT_TIMEOUT));
+                    self.device_generation = self.device_generation.wrapping_add(1);
+                    self.refresh_cooldown = Some(anim::Progress::new(REFRESH_COOLDOWN));
+                    return Task::batch([fetch_peripherals(), after(DEVICE_DETECT_TIMEOUT, Timer::Detecting), after(REFRESH_COOLDOWN, Timer::RefreshCooldown)]);
+                }
+            }
+            Message::DevicesLost => {
+                let attempt = self.reconnect_attempts.map_or(0, |n| n.saturating_add(1));
+                self.reconnect_attempts = Some(attempt);
+                let delay = RECONNECT_BASE_DELAY.saturating_mul(1 << attempt.min(5)).min(RECONNECT_MAX_DELAY);
+                tracing::warn!("Audio device subscription ended, reconnecting in {delay:?}");
+                return Task::perform(tokio::time::sleep(delay), |_| cosmic::Action::from(Message::Reconnect));
+            }
+            Message::Reconnect => {
+                self.model = css::Model::default();
+                self.detecting = Some(anim::Progress::new(DEVICE_DETECT_TIMEOUT));
+                self.device_generation = self.device_generation.wrapping_add(1);
+                return after(DEVICE_DETECT_TIMEOUT, Timer::Detecting);
+            }
+            Message::BluetoothDevices(devices) => self.bt_devices = devices,
+            Message::SinkNodes(nodes) => self.sink_nodes = nodes,
+            // Streams come and go with the apps, so look again whenever focus moves
+            Message::FocusedApp(app) => {
+                self.focused_app = app;
+                return Task::perform(wpctl::app_streams(), |streams| cosmic::Action::from(Message::AppStreams(streams)));
+            }
+            Message::AppStreams(streams) => self.app_streams = streams,
+            Message::MonitorSources(names) => self.monitor_sources = names,
+            Message::ChooseCombined => self.combine = if self.combine.is_some() { None } else { Some(Vec::new()) },
+            Message::ToggleCombined(name) => if let Some(chosen) = self.combine.as_mut() {
+                match chosen.iter().position(|n| *n == name) {
+                    Some(i) => { chosen.remove(i); }
+                    None => chosen.push(name),
+                }
+            },
+            Message::CreateCombinedSink(names) => {
+                self.combine = None;
+                let ids = names.iter().filter_map(|name| self.sink_nodes.iter().find(|n| n.name == *name).map(|n| n.id)).collect();
+                self.pending_default = Some(PendingDefault::Named(wpctl::COMBINED_SINK.to_owned()));
+                return Task::perform(wpctl::combine_sinks(ids, self.combined_module.take()), |module| cosmic::Action::from(Message::CombinedSinkCreated(module)));
+            }
+            Message::CombinedSinkCreated(module) => {
+                self.combined_module = module;
+                if module.is_none() {
+                    tracing::warn!("Failed to create a combined sink");
+                    self.pending_default = None;
+                }
+            }
+            // The slider follows the drag; PipeWire gets throttled updates and the final value on release
+            Message::SetDeviceVolume(name, val) => {
+                let Some(node) = self.sink_nodes.iter_mut().find(|n| n.name == name) else { return Task::none() };
+                node.volume = val;
+                let id = node.id;
+                if let Some(last) = self.device_last_update { if last.elapsed().as_millis() < 50 { return Task::none(); } }
+                self.device_last_update = Some(Instant::now());
+                self.run_command(AudioCommand::SetVolume(Target::Node(id), val));
+            }
+            Message::CommitDeviceVolume(name) => if let Some(node) = self.sink_nodes.iter().find(|n| n.name == name) {
+                self.device_last_update = None;
+                self.run_command(AudioCommand::SetVolume(Target::Node(node.id), node.volume));
+            },
+            Message::Batteries(batteries) => self.batteries = batteries,
+            Message::InputChannels(channels) => self.input_channels = channels,
+            Message::InputPeaks(peaks) => self.input_peaks = peaks,
+            Message::ToggleMeterChannel(channel) => {
+                let count = self.input_peaks.len() as u8;
+                let mut channels = self.config.input_meter_channels.clone();
+                if channels.is_empty() { channels = (0..count).collect(); }
+                match channels.iter().position(|c| *c == channel) {
+                    // The meter always follows at least one channel
+                    Some(_) if channels.len() == 1 => return Task::none(),
+                    Some(i) => { channels.remove(i); }
+                    None => { channels.push(channel); channels.sort_unstable(); }
+                }
+                if (0..count).all(|c| channels.contains(&c)) { channels.clear(); }
+                let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+                    .and_then(|config| self.config.set_input_meter_channels(&config, channels));
+                if let Err(err) = result { tracing::error!("Failed to save input meter channels: {err:?}"); }
+            }
+            Message::ToggleMeterLayout => {
+                let layout = match self.config.input_meter_layout { MeterLayout::Combined => MeterLayout::PerChannel, MeterLayout::PerChannel => MeterLayout::Combined };
+                let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+                    .and_then(|config| self.config.set_input_meter_layout(&config, layout));
+                if let Err(err) = result { tracing::error!("Failed to save input meter layout: {err:?}"); }
+            }
+            Message::CheckNightCap => {
+                if self.config.night_cap.is_none() {
+                    return self.update(Message::LocalHour(None));
+                }
+                return Task::perform(local_hour(), |hour| cosmic::Action::from(Message::LocalHour(hour)));
+            }
+            Message::LocalHour(hour) => {
+                let was_active = self.night_cap_active;
+                self.night_cap_active = self.config.night_cap.is_some() && hour.is_some_and(|h| self.config.night_cap_applies(h));
+                self.update_limits();
+                // Bring the volume down once when the cap kicks in
+                if let Some(cap) = self.volume_cap().filter(|cap| !was_active && self.main_sink().0 > *cap) {
+                    self.apply_sink_volume(cap);
+                }
+            }
+            Message::DismissScrollTip => {
+                let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+                    .and_then(|config| self.config.set_seen_scroll_tip(&config, true));
+                if let Err(err) = result { tracing::error!("Failed to save dismissed scroll tip: {err:?}"); }
+            }
+            Message::CheckSinkRunning => return Task::perform(wpctl::default_sink_running(), |running| cosmic::Action::from(Message::SinkRunning(running))),
+            Message::SinkChanged => if self.sink_changes.is_none() {
+                self.sink_changes = Some(anim::Progress::new(SINK_CHANGE_DEBOUNCE));
+                return after(SINK_CHANGE_DEBOUNCE, Timer::SinkChanges);
+            },
+            Message::SinkRunning(running) => self.sink_running = running,
+            Message::SinkFormat(format) => self.sink_format = format,
+            Message::ConnectBluetooth(address) => {
+                self.pending_default = Some(PendingDefault::Bluetooth(address.clone()));
+                return Task::perform(bluetooth::connect(address), |ok| cosmic::Action::from(Message::BluetoothConnected(ok)));
+            }
+            Message::BluetoothConnected(ok) => {
+                if !ok {
+                    tracing::warn!("Failed to connect Bluetooth device {:?}", self.pending_default.take());
+                }
+                return fetch_peripherals();
+            }
+            // A connected headset's sink may be listed before its address is known
+            Message::BluetoothAddresses(addresses) => {
+                self.bt_addresses = addresses;
+                return self.apply_pending_default();
+            }
 
-            Message::Subscription(m) => return self.model.update(m).map(|m| cosmic::Action::from(Message::Subscription(m))),
-            Message::Mpris(MprisUpdate::Player(p)) => self.player_status = Some(p),
-            Message::Mpris(MprisUpdate::Finished | MprisUpdate::Setup) => self.player_status = None,
-            Message::ConfigChanged(c) => self.config = c,
+            Message::Subscription(m) => {
+                self.reconnect_attempts = None;
+                let (sink_before, source_before) = (self.model.sink_volume, self.model.source_volume);
+                let (sink_shown, source_shown) = (self.sink_volume(), self.source_volume());
+                let (sink_name, source_name) = (self.active_sink_name().cloned(), self.active_source_name().cloned());
+                let (sinks_before, sources_before) = (self.model.sinks().to_vec(), self.model.sources().to_vec());
+                let mut task = self.model.update(m).map(|m| cosmic::Action::from(Message::Subscription(m)));
+                if self.popup.is_some() {
+                    self.track_hotplug(DeviceKind::Sink, sinks_before, self.model.sinks().to_vec());
+                    self.track_hotplug(DeviceKind::Source, sources_before.clone(), self.model.sources().to_vec());
+                }
+                if !self.model.sinks().is_empty() || !self.model.sources().is_empty() { self.detecting = None; }
+                self.drop_stale_drags(sink_before, source_before);
+                // Glide the sliders to externally changed volumes; drags stay 1:1
+                if self.popup.is_some() {
+                    if self.model.sink_volume != sink_before && self.sink_drag_val.is_none() && self.main_node().is_none() {
+                        self.sink_anim = Some(anim::Tween::new(sink_shown as f32, self.model.sink_volume as f32, VOLUME_ANIM_DURATION));
+                    }
+                    if self.model.source_volume != source_before && self.source_drag_val.is_none() {
+                        self.source_anim = Some(anim::Tween::new(source_shown as f32, self.model.source_volume as f32, VOLUME_ANIM_DURATION));
+                    }
+                }
+                // The first default comes from enumeration at startup, not a switch
+                if self.config.notify_on_device_change && sink_name.is_some() && self.active_sink_name() != sink_name.as_ref() {
+                    if let Some(name) = self.active_sink_name().cloned() {
+                        self.pending_notification = Some((name, anim::Progress::new(NOTIFY_DEBOUNCE)));
+                        task = Task::batch([task, after(NOTIFY_DEBOUNCE, Timer::Notification)]);
+                    }
+                }
+                if self.active_sink_name() != sink_name.as_ref() && self.external_default_change(DeviceKind::Sink, sink_name.is_some()) {
+                    self.default_pulse = Some((DeviceKind::Sink, anim::Progress::new(PULSE_DURATION)));
+                }
+                if self.active_source_name() != source_name.as_ref() && self.external_default_change(DeviceKind::Source, source_name.is_some()) {
+                    self.default_pulse = Some((DeviceKind::Source, anim::Progress::new(PULSE_DURATION)));
+                }
+                // Which sources are monitors is only known from their properties
+                if self.model.sources() != sources_before.as_slice() {
+                    task = Task::batch([task, Task::perform(wpctl::monitor_sources(), |names| cosmic::Action::from(Message::MonitorSources(names)))]);
+                }
+                // parec records as many channels as it's asked for, so the meter needs the real count
+                if self.popup.is_some() && self.config.input_meter && self.active_source_name() != source_name.as_ref() {
+                    task = Task::batch([task, Task::perform(wpctl::default_source_channels(), |channels| cosmic::Action::from(Message::InputChannels(channels)))]);
+                }
+                if self.popup.is_some() && self.active_sink_name() != sink_name.as_ref() {
+                    self.sink_format = None;
+                    task = Task::batch([task, Task::perform(wpctl::default_sink_format(), |format| cosmic::Action::from(Message::SinkFormat(format)))]);
+                }
+                if let Some((_, val)) = self.pending_volume_restore.take_if(|(name, _)| self.model.active_sink().and_then(|i| self.model.sinks().get(i)) == Some(name)) {
+                    let val = self.capped(val);
+                    self.run_command(AudioCommand::SetVolume(Target::DefaultSink, val));
+                    if val > 0 && self.main_node().is_none() { self.pre_mute_volume = None; }
+                }
+                // The pinned output's level and presence only come from `wpctl`
+                if self.config.pinned_sink.is_some() && (self.model.sinks() != sinks_before.as_slice() || self.model.sink_volume != sink_before) {
+                    task = Task::batch([task, Task::perform(wpctl::sinks(), |nodes| cosmic::Action::from(Message::SinkNodes(nodes)))]);
+                }
+                if self.popup.is_some() && (self.model.sinks() != sinks_before.as_slice() || self.model.sources() != sources_before.as_slice()) {
+                    task = Task::batch([task, Task::perform(wpctl::bluetooth_addresses(), |addresses| cosmic::Action::from(Message::BluetoothAddresses(addresses)))]);
+                }
+                return Task::batch([task, self.apply_pending_default()]);
+            }
+            Message::Mpris(MprisUpdate::Player(p)) => {
+                self.reported_player = None;
+                if self.player_status.as_ref().is_none_or(|s| s.title != p.title) { self.marquee_start = Some(Instant::now()); }
+                // Confirms that next/previous advanced, so a player appearing doesn't count
+                if self.config.track_change_flash && self.popup.is_some() && self.player_status.as_ref().is_some_and(|s| s.title != p.title) {
+                    self.track_flash = Some(anim::Progress::new(TRACK_FLASH));
+                }
+                let art = p.icon.clone().filter(|path| self.config.art_accent && self.art_accent.as_ref().is_none_or(|(last, _)| last != path));
+                self.player_status = Some(p);
+                let accent = match art {
+                    Some(path) => Task::perform(accent::dominant_color(path.clone()), move |color| cosmic::Action::from(Message::ArtAccent(path.clone(), color))),
+                    None => Task::none(),
+                };
+                return Task::batch([accent, self.scale_art()]);
+            }
+            Message::ArtScaled(path, width, handle) => self.scaled_art = Some((path, width, handle)),
+            // Art without a clear color keeps the theme's accent
+            Message::ArtAccent(path, color) => self.art_accent = Some((path, color)),
+            Message::Mpris(MprisUpdate::Finished | MprisUpdate::Setup) => (self.player_status, self.reported_player) = (None, None),
+            Message::ConfigChanged(mut c) => {
+                // Write upgraded configs back so every key is present at the current version
+                c.validate();
+                if c.migrate() {
+                    let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION).and_then(|config| c.write_entry(&config));
+                    if let Err(err) = result { tracing::error!("Failed to save migrated config: {err:?}"); }
+                }
+                self.config = c;
+                let maxima = (self.max_sink_volume, self.max_source_volume);
+                self.update_limits();
+                let mut task = Task::batch([self.update(Message::CheckNightCap), self.clamp_to_limits(maxima)]);
+                if self.config.input_meter && self.popup.is_some() && self.input_channels.is_none() {
+                    task = Task::batch([task, Task::perform(wpctl::default_source_channels(), |channels| cosmic::Action::from(Message::InputChannels(channels)))]);
+                }
+                if self.config.follow_focused_app {
+                    task = Task::batch([task, Task::perform(wpctl::app_streams(), |streams| cosmic::Action::from(Message::AppStreams(streams)))]);
+                } else {
+                    (self.focused_app, self.app_streams) = (None, Vec::new());
+                }
+                // Later changes come from `pactl subscribe`
+                if self.config.playing_indicator {
+                    task = Task::batch([task, self.update(Message::CheckSinkRunning)]);
+                }
+                // Otherwise the pinned output is only found once the popup opens
+                if self.config.pinned_sink.is_some() {
+                    task = Task::batch([task, Task::perform(wpctl::sinks(), |nodes| cosmic::Action::from(Message::SinkNodes(nodes)))]);
+                }
+                return task;
+            }
+            // Limits are otherwise only recomputed when the popup opens
+            Message::AmplificationChanged => {
+                let maxima = (self.max_sink_volume, self.max_source_volume);
+                self.update_limits();
+                return self.clamp_to_limits(maxima);
+            }
             
             Message::TogglePopup => {
-                if let Some(p) = self.popup.take() { return destroy_popup(p); }
+                if self.popup.is_some() {
+                    return self.close_popup();
+                }
+                // The panel surface can be missing early on or while the compositor reconfigures outputs
+                let Some(parent) = self.core.main_window_id() else {
+                    tracing::warn!("No panel window to attach the popup to");
+                    return Task::none();
+                };
                 let new_id = window::Id::unique();
                 self.popup.replace(new_id);
-                self.timeline = Timeline::new();
-                (self.max_sink_volume, self.sink_breakpoints) = if amplification_sink() { (150, &[100][..]) } else { (100, &[][..]) };
-                (self.max_source_volume, self.source_breakpoints) = if amplification_source() { (150, &[100][..]) } else { (100, &[][..]) };
-                return get_popup(self.core.applet.get_popup_settings(self.core.main_window_id().unwrap(), new_id, None, None, None));
-            }
-            Message::OutputToggle => self.is_open = if self.is_open == IsOpen::Output { IsOpen::None } else { IsOpen::Output },
-            Message::InputToggle => self.is_open = if self.is_open == IsOpen::Input { IsOpen::None } else { IsOpen::Input },
-            Message::CloseRequested(id) => if Some(id) == self.popup { self.popup = None; },
+                self.update_limits();
+                let mut popup_settings = self.core.applet.get_popup_settings(parent, new_id, None, None, None);
+                if let Some(width) = self.popup_width() {
+                    popup_settings.positioner.size_limits = iced::Limits::NONE.min_width(width).max_width(width);
+                }
+                let streams = match self.config.follow_focused_app {
+                    true => Task::perform(wpctl::app_streams(), |streams| cosmic::Action::from(Message::AppStreams(streams))),
+                    false => Task::none(),
+                };
+                let channels = match self.config.input_meter {
+                    true => Task::perform(wpctl::default_source_channels(), |channels| cosmic::Action::from(Message::InputChannels(channels))),
+                    false => Task::none(),
+                };
+                // The scale may have changed while the popup was closed
+                return Task::batch([get_popup(popup_settings), fetch_peripherals(), self.scale_art(), streams, channels]);
+            }
+            Message::OutputToggle if self.config.device_pages => self.view_mode = ViewMode::Outputs,
+            Message::InputToggle if self.config.device_pages => self.view_mode = ViewMode::Inputs,
+            Message::OutputToggle => self.toggle_revealer(IsOpen::Output),
+            Message::InputToggle => self.toggle_revealer(IsOpen::Input),
+            Message::ShowMain => self.view_mode = ViewMode::Main,
+            Message::CloseRequested(id) => if Some(id) == self.popup { self.popup = None; self.reset_popup_state(); },
             _ => {}
         }
         Task::none()
     }
 
     fn subscription(&self) -> iced::Subscription<Message> {
-        iced::Subscription::batch([
-            self.timeline.as_subscription().map(|(_, now)| Message::Frame(now)),
+        let mut subscriptions = vec![
             self.core.watch_config(Self::APP_ID).map(|u| Message::ConfigChanged(u.config)),
-            mpris_subscription::mpris_subscription(0).map(Message::Mpris),
-            iced::Subscription::run(|| css::watch().map(Message::Subscription)),
-        ])
+            self.core.watch_config::<AmplificationConfig>(config::AUDIO_CONFIG).map(|_| Message::AmplificationChanged),
+            mpris_subscription::mpris_subscription(0, self.config.preferred_player.clone()).map(Message::Mpris),
+            audio_command::queue().map(Message::CommandQueue),
+            iced::event::listen_with(modifiers_changed),
+            // The stream ends when PipeWire restarts; report it so the subscription can be restarted.
+            // It is only built once iced starts it, not on every call here
+            iced::Subscription::run_with_id(("css-watch", self.device_generation), iced::futures::stream::once(async { css::watch() }).flatten()
+                .map(Message::Subscription).chain(iced::futures::stream::once(async { Message::DevicesLost }))),
+        ];
+        // Keys and surface events only matter while the popup is open
+        if self.popup.is_some() {
+            subscriptions.push(iced::event::listen_with(popup_key));
+            subscriptions.push(iced::event::listen_with(popup_done));
+        }
+        if self.is_animating() {
+            subscriptions.push(iced::time::every(anim::FRAME_INTERVAL).map(Message::Frame));
+        }
+        if self.marquee_active() {
+            subscriptions.push(iced::time::every(MARQUEE_STEP).map(Message::Frame));
+        }
+        // Kept until the return even if the setting was turned off meanwhile, so the mic gets unmuted
+        if self.config.auto_mute_mic_on_idle || self.muted_for_away.is_some() {
+            subscriptions.push(session::away().map(Message::SessionAway));
+        }
+        if self.config.clip_warning && self.popup.is_some() {
+            subscriptions.push(peak::levels("@DEFAULT_MONITOR@", 2).map(Message::OutputPeaks));
+        }
+        // Waits for the channel count, which is part of its id, so opening doesn't start parec twice
+        if let Some(channels) = self.input_channels.filter(|_| self.config.input_meter && self.popup.is_some()) {
+            subscriptions.push(peak::levels("@DEFAULT_SOURCE@", channels).map(Message::InputPeaks));
+        }
+        if self.config.follow_focused_app {
+            subscriptions.push(focus::focused_app().map(Message::FocusedApp));
+        }
+        if self.config.dbus_control {
+            subscriptions.push(dbus_control::subscription().map(Message::DbusControl));
+        }
+        if self.config.playing_indicator || self.config.pinned_sink.is_some() || self.config.follow_focused_app {
+            subscriptions.push(wpctl::sink_changes().map(|()| Message::SinkChanged));
+        }
+        if self.config.night_cap.is_some() {
+            subscriptions.push(iced::time::every(Duration::from_secs(60)).map(|_| Message::CheckNightCap));
+        }
+        // Keep the progress bar moving while something plays, no faster than it advances a pixel
+        if let Some(s) = self.player_status.as_ref().filter(|s| self.popup.is_some() && matches!(s.status, PlaybackStatus::Playing)) {
+            let tick = s.length.map_or(Duration::from_secs(1), |l| self.progress_pixel(l).max(Duration::from_secs(1)));
+            subscriptions.push(iced::time::every(tick).map(Message::Frame));
+        }
+        iced::Subscription::batch(subscriptions)
     }
 
     fn view(&self) -> Element<'_, Message> {
-        let btn = self.core.applet.icon_button(self.output_icon_name()).on_press_down(Message::TogglePopup);
-        let btn = crate::mouse_area::MouseArea::new(btn).on_mouse_wheel(|delta| {
-            let y = match delta { iced::mouse::ScrollDelta::Lines { y, .. } => y, iced::mouse::ScrollDelta::Pixels { y, .. } => y.signum() };
-            let new_vol = (self.model.sink_volume as i32 + (y * 5.0) as i32).clamp(0, 100) as u32;
-            Message::SetSinkVolume(new_vol)
-        });
-        self.core.applet.autosize_window(Element::from(btn)).into()
+        let btn = self.panel_icon_button(self.output_icon_name()).on_press_down(Message::PanelPress);
+        let mut btn = crate::mouse_area::MouseArea::new(btn);
+        if self.config.open_on_hover {
+            btn = btn.on_mouse_enter(Message::HoverIcon(true)).on_mouse_exit(Message::HoverIcon(false));
+        }
+        // The popup must stay reachable when left-click is rebound
+        if self.config.primary_action != PanelAction::TogglePopup {
+            btn = btn.on_right_press(Message::TogglePopup);
+        }
+        if self.config.double_tap_mute {
+            btn = btn.on_double_tap(Message::PanelDoubleTap);
+        }
+        let btn: Element<'_, Message> = btn.on_mouse_wheel(|delta| Message::SetSinkVolume(self.scrolled_volume(self.main_sink().0, self.capped(100), delta))).into();
+        let btn = if self.config.playing_indicator && self.sink_running { iced::widget::stack![btn, playing_dot()].into() } else { btn };
+        // Vertical panels have no room for a title
+        let content = match self.player_status.as_ref().filter(|_| self.config.panel_media_mode && self.core.applet.is_horizontal()) {
+            Some(s) => {
+                let title = ellipsize(s.title.as_deref().unwrap_or_default(), PANEL_TITLE_CHARS);
+                let (icon_name, label, action) = match s.status {
+                    PlaybackStatus::Playing => (PAUSE, fl!("pause"), s.can_pause.then_some(MprisRequest::Pause)),
+                    _ => (if self.rtl { PLAY_RTL } else { PLAY }, fl!("play"), s.can_play.then_some(MprisRequest::Play)),
+                };
+                self.mirrored(vec![
+                    btn,
+                    button::custom(self.core.applet.text(title)).class(cosmic::theme::Button::AppletIcon).on_press_down(Message::TogglePopup).into(),
+                    labelled(self.panel_icon_button(icon_name), label).on_press_maybe(action.map(Message::MprisRequest)).into(),
+                ]).align_y(Alignment::Center).into()
+            }
+            None => btn,
+        };
+        self.core.applet.autosize_window(content).into()
     }
 
     fn view_window(&self, _id: window::Id) -> Element<'_, Message> {
+        let content = match self.view_mode {
+            ViewMode::Main => self.main_content(),
+            ViewMode::Outputs => self.device_page(fl!("output"), self.active_sink_name().cloned(), self.sink_entries()).push(self.combine_row()),
+            ViewMode::Inputs => self.device_page(fl!("input"), self.active_source_name().cloned(), self.source_entries()),
+        };
+
+        let content = match self.reconnect_attempts {
+            Some(_) => column![padded_control(text::caption(fl!("reconnecting"))), content],
+            None => content,
+        };
+        let content = if self.config.seen_scroll_tip { content } else { column![self.scroll_tip_row(), content] };
+        let content = container(content.align_x(self.start()).padding([8, 0]));
+        let content = match self.popup_width() { Some(w) => content.width(Length::Fixed(w)), None => content };
+        let content: Element<'_, Message> = if self.config.open_on_hover {
+            crate::mouse_area::MouseArea::new(content).on_mouse_enter(Message::HoverPopup(true)).on_mouse_exit(Message::HoverPopup(false)).into()
+        } else {
+            content.into()
+        };
+        self.core.applet.popup_container(content).into()
+    }
+}
+
+impl Audio {
+    fn main_content(&self) -> cosmic::iced::widget::Column<'_, Message, Theme, Renderer> {
         let Spacing { space_xxs, space_s, .. } = theme::active().cosmic().spacing;
         
-        let sink_vol = self.sink_drag_val.unwrap_or(self.model.sink_volume);
-        let source_vol = self.source_drag_val.unwrap_or(self.model.source_volume);
+        let sink_vol = self.sink_volume();
+        let source_vol = self.source_volume();
         
         // --- 1. OUTPUT VOLUME ---
-        let mut content = column![
-            padded_control(row![
-                button::icon(icon::from_name(self.output_icon_name()).size(24).symbolic(true))
-                    .class(cosmic::theme::Button::Icon).on_press(Message::ToggleSinkMute),
-                slider(0..=self.max_sink_volume, sink_vol, Message::DragSink)
-                    .width(Length::FillPortion(5)).breakpoints(self.sink_breakpoints)
-                    .on_release(Message::CommitSink),
-                container(text(format!("{}%", sink_vol)).size(16)).width(Length::FillPortion(1)).align_x(Alignment::End)
-            ].spacing(12).align_y(Alignment::Center)),
-            
-            revealer(self.is_open == IsOpen::Output, fl!("output"), 
-                self.model.active_sink().and_then(|i| self.model.sinks().get(i)).cloned().unwrap_or("No Device".into()), 
-                self.model.sinks(), Message::OutputToggle, Message::SetDefaultSink)
-        ];
+        let output = column![
+            self.volume_row(VolumeControl {
+                icon: self.output_icon_name(), volume: sink_vol, max: self.max_sink_volume, limit: self.capped(self.max_sink_volume),
+                breakpoints: &self.sink_breakpoints, on_mute: Message::ToggleSinkMute,
+                muted: self.sink_muted(),
+                mute_label: if self.sink_muted() { fl!("unmute-output") } else { fl!("mute-output") },
+                extra: None,
+                on_drag: Message::DragSink, on_release: Message::CommitSink, on_set: Message::SetSinkVolume,
+                entry: self.sink_entry.as_deref(), entry_id: &SINK_ENTRY_ID, on_edit: Message::EditSinkVolume, on_submit: Message::SetSinkVolumeExact,
+            }),
+        ].push_maybe(self.boost_prompt.is_some().then(|| self.boost_prompt_row()))
+        .push_maybe(self.focused_stream().map(|n| padded_control(text::caption(fl!("controlling-app", app = n.name.clone())))))
+        .push_maybe(self.clipping.is_some().then(|| self.clipping_row()))
+        .push(revealer(self.reveal_fraction(IsOpen::Output), fl!("output"), 
+            self.selection_caption(DeviceKind::Sink, self.active_sink_name().map_or_else(|| self.no_device_label(), |n| self.display_name(n))), 
+            self.active_sink_battery().map(|p| fl!("battery-level", level = localize::percent(p.into()))),
+            self.sink_entries(), self.max_sink_volume, self.empty_list_label(), self.settings_button(SoundPage::Output), self.rtl, Message::OutputToggle))
+        .push_maybe(self.sink_format.clone().filter(|_| self.reveal_fraction(IsOpen::Output) >= 1.0).map(|f| padded_control(text::caption(f))))
+        .push_maybe((self.reveal_fraction(IsOpen::Output) >= 1.0).then(|| self.combine_row()));
 
         // --- 2. INPUT VOLUME ---
-        content = content.push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]));
-        content = content.push(column![
-             padded_control(row![
-                button::icon(icon::from_name(self.input_icon_name()).size(24).symbolic(true))
-                    .class(cosmic::theme::Button::Icon).on_press(Message::ToggleSourceMute),
-                slider(0..=self.max_source_volume, source_vol, Message::DragSource)
-                    .width(Length::FillPortion(5)).breakpoints(self.source_breakpoints)
-                    .on_release(Message::CommitSource),
-                container(text(format!("{}%", source_vol)).size(16)).width(Length::FillPortion(1)).align_x(Alignment::End)
-            ].spacing(12).align_y(Alignment::Center)),
-            
-            revealer(self.is_open == IsOpen::Input, fl!("input"), 
-                self.model.active_source().and_then(|i| self.model.sources().get(i)).cloned().unwrap_or("No Device".into()), 
-                self.model.sources(), Message::InputToggle, Message::SetDefaultSource)
-        ]);
+        let input = column![
+             self.volume_row(VolumeControl {
+                icon: self.input_icon_name(), volume: source_vol, max: self.max_source_volume, limit: self.max_source_volume,
+                breakpoints: &self.source_breakpoints, on_mute: Message::ToggleSourceMute,
+                muted: self.model.source_mute,
+                mute_label: if self.model.source_mute { fl!("unmute-input") } else { fl!("mute-input") },
+                extra: Some(self.mic_boost_button()),
+                on_drag: Message::DragSource, on_release: Message::CommitSource, on_set: Message::SetSourceVolume,
+                entry: self.source_entry.as_deref(), entry_id: &SOURCE_ENTRY_ID, on_edit: Message::EditSourceVolume, on_submit: Message::SetSourceVolumeExact,
+            }),
+        ].push_maybe((self.config.input_meter && !self.input_peaks.is_empty()).then(|| self.input_meter_row()))
+        .push(
+            revealer(self.reveal_fraction(IsOpen::Input), fl!("input"), 
+                self.selection_caption(DeviceKind::Source, self.active_source_name().map_or_else(|| self.no_device_label(), |n| self.display_name(n))), 
+                None, self.source_entries(), self.max_source_volume, self.empty_list_label(), self.settings_button(SoundPage::Input), self.rtl, Message::InputToggle));
+
+        let (first, second) = match self.config.primary_slider { PrimarySlider::Output => (output, input), PrimarySlider::Input => (input, output) };
+        let mut content = column![first, padded_control(divider::horizontal::default()).padding([space_xxs, space_s]), second];
 
-        // --- 3. VERTICAL MEDIA WIDGET ---
+        // --- 3. MEDIA WIDGET ---
         if let Some(s) = self.player_status.as_ref() {
              content = content.push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]));
              
-             // ROW 1: ALBUM ART (Full Width + Margin)
-             let art = if let Some(path) = s.icon.clone() {
-                 // Length::Fill makes it fill the container, Padding creates the margin
-                 container(image(path).width(Length::Fill)).padding([0, 24]) 
-             } else {
-                 container(icon::from_name("audio-x-generic-symbolic").size(96))
-             };
-             
-             // ROW 2: CONTROLS
+             // CONTROLS
+             let (go_back, go_next, play) = if self.rtl { (GO_BACK_RTL, GO_NEXT_RTL, PLAY_RTL) } else { (GO_BACK, GO_NEXT, PLAY) };
              let mut controls = Vec::new();
-             if s.can_go_previous { controls.push(media_btn(GO_BACK, Message::MprisRequest(MprisRequest::Previous))); }
-             let (icon_name, action) = match s.status {
-                PlaybackStatus::Playing => (PAUSE, MprisRequest::Pause),
-                _ => (PLAY, MprisRequest::Play)
+             if let Some(on) = s.shuffle.filter(|_| s.can_control) {
+                 controls.push(self.media_btn(self.named_icon(SHUFFLE), fl!("shuffle"), Message::MprisRequest(MprisRequest::SetShuffle(!on)), on));
+             }
+             if s.can_go_previous { controls.push(self.media_btn(self.named_icon(go_back), fl!("previous-track"), Message::MprisRequest(MprisRequest::Previous), self.media_pressed(&MprisRequest::Previous))); }
+             // Paused/stopped players may not be able to resume, so ask what they support
+             let (icon_name, label, action) = match s.status {
+                PlaybackStatus::Playing => (PAUSE, fl!("pause"), s.can_pause.then_some(MprisRequest::Pause)),
+                _ => (play, fl!("play"), s.can_play.then_some(MprisRequest::Play))
+             };
+             let toggled = self.media_pressed(&MprisRequest::Play) || self.media_pressed(&MprisRequest::Pause);
+             controls.push(self.media_btn(self.named_icon(icon_name), label, action.map(Message::MprisRequest), toggled));
+             if self.config.show_stop_button && s.can_control { controls.push(self.media_btn(self.named_icon(STOP), fl!("stop"), Message::MprisRequest(MprisRequest::Stop), self.media_pressed(&MprisRequest::Stop))); }
+             if s.can_go_next { controls.push(self.media_btn(self.named_icon(go_next), fl!("next-track"), Message::MprisRequest(MprisRequest::Next), self.media_pressed(&MprisRequest::Next))); }
+             // Cycles off -> playlist -> track
+             if let Some(status) = s.loop_status.as_ref().filter(|_| s.can_control) {
+                 let (icon_name, label, next, active) = match status {
+                     LoopStatus::None => (REPEAT, fl!("repeat"), LoopStatus::Playlist, false),
+                     LoopStatus::Playlist => (REPEAT, fl!("repeat"), LoopStatus::Track, true),
+                     LoopStatus::Track => (REPEAT_SONG, fl!("repeat-track"), LoopStatus::None, true),
+                 };
+                 controls.push(self.media_btn(self.named_icon(icon_name), label, Message::MprisRequest(MprisRequest::SetLoopStatus(next)), active));
+             }
+             // Cycles through the supported speeds, labelled with the current one
+             let rates: Vec<f64> = RATES.into_iter().filter(|r| (s.min_rate..=s.max_rate).contains(r)).collect();
+             if let Some(rate) = s.rate.filter(|_| s.can_control && rates.len() > 1) {
+                 let next = rates.iter().copied().find(|r| *r > rate + f64::EPSILON).unwrap_or(rates[0]);
+                 controls.push(labelled(button::custom(text::caption(fl!("playback-rate", rate = format!("{rate}")))), fl!("playback-speed"))
+                     .class(cosmic::theme::Button::Text).on_press(Message::MprisRequest(MprisRequest::SetRate(next))).into());
+             }
+             let controls_row = self.mirrored(controls).spacing(16).align_y(Alignment::Center);
+
+             // Live streams report no length, so they get neither a bar nor scroll-seek
+             let progress = s.length.filter(|l| !l.is_zero()).zip(s.position_now()).map(|(length, pos)| {
+                 // Snap to whole pixels so animation frames don't redraw sub-pixel progress
+                 let pixel = self.progress_pixel(length).as_secs_f32();
+                 let shown = if pixel > 0.0 { (pos.as_secs_f32() / pixel).floor() * pixel } else { pos.as_secs_f32() };
+                 let mut bar = progress_bar(0.0..=length.as_secs_f32(), shown).height(Length::Fixed(4.0));
+                 if let Some(color) = self.art_accent() {
+                     bar = bar.class(cosmic::theme::ProgressBar::Custom(Box::new(move |theme: &Theme| {
+                         let style = iced::widget::progress_bar::Catalog::style(theme, &cosmic::theme::ProgressBar::Primary);
+                         iced::widget::progress_bar::Style { bar: color.into(), ..style }
+                     })));
+                 }
+                 let area = crate::mouse_area::MouseArea::new(bar);
+                 if s.can_seek {
+                     let step = Duration::from_secs(self.config.seek_step.into());
+                     area.on_mouse_wheel(move |delta| {
+                         let target = if self.scroll_y(delta) > 0.0 { (pos + step).min(length) } else { pos.saturating_sub(step) };
+                         Message::MprisRequest(MprisRequest::SetPosition(target))
+                     })
+                 } else { area }
+             });
+
+             let title = s.title.clone().unwrap_or_default().into_owned();
+             let artist = s.artists.as_ref().map(|a| a.join(", ")).unwrap_or_else(|| fl!("unknown-artist"));
+             let album = s.album.as_ref().filter(|_| self.config.show_album).map(|album| match s.track_number {
+                 Some(n) => fl!("album-track", album = album.to_string(), track = n),
+                 None => album.to_string(),
+             });
+
+             // Swipe the art like a carousel: towards the start for the next track
+             let (can_next, can_previous, rtl) = (s.can_go_next, s.can_go_previous, self.rtl);
+             let swipeable = move |art: Element<'static, Message>| -> Element<'static, Message> {
+                 crate::mouse_area::MouseArea::new(art)
+                     .on_tap(Message::MprisRequest(MprisRequest::Raise))
+                     .on_swipe(move |swipe| if (swipe == crate::mouse_area::Swipe::Left) != rtl {
+                         can_next.then_some(Message::MprisRequest(MprisRequest::Next))
+                     } else {
+                         can_previous.then_some(Message::MprisRequest(MprisRequest::Previous))
+                     })
+                     .into()
+             };
+
+             // Which app is playing, for telling several players apart
+             let source = s.app_name.clone().map(|name| {
+                 let app_icon: Element<'_, Message> = match s.app_icon.as_deref() {
+                     Some(path) if path.starts_with('/') => icon::icon(icon::from_path(path.into())).size(16).into(),
+                     Some(name) => icon::from_name(name).size(16).into(),
+                     None => self.named_icon(AUDIO_GENERIC).size(16).into(),
+                 };
+                 row![app_icon, text::caption(ellipsize(&name, COMPACT_TEXT_CHARS))].spacing(4).align_y(Alignment::Center)
+             });
+
+             let media: Element<'_, Message> = match self.config.media_layout {
+                 MediaLayout::Vertical => {
+                     // ROW 1: ALBUM ART (Full Width + Margin)
+                     let art = if let Some(art) = self.art_handle() {
+                         // Length::Fill makes it fill the container, Padding creates the margin
+                         container(swipeable(image(art).width(Length::Fill).into())).padding([0, self.art_margin()]) 
+                     } else {
+                         container(swipeable(self.named_icon(AUDIO_GENERIC).size(96).into()))
+                     };
+
+                     // ROW 2: CONTROLS, ROW 3: TITLE, ROW 4: ARTIST
+                     let mut media_column = column![
+                         art,
+                         controls_row,
+                     ].spacing(12).align_x(Alignment::Center).width(Length::Fill);
+                     if let Some(progress) = progress { media_column = media_column.push(container(progress).padding([0, self.art_margin()])); }
+                     media_column
+                         .push_maybe(source)
+                         .push(copyable(self.title_label(&title).width(Length::Fill).align_x(Alignment::Center)))
+                         .push(text::caption(ellipsize(&artist, MEDIA_TEXT_CHARS)).width(Length::Fill).align_x(Alignment::Center))
+                         .push_maybe(album.map(|a| text::caption(ellipsize(&a, MEDIA_TEXT_CHARS)).width(Length::Fill).align_x(Alignment::Center)))
+                         .into()
+                 }
+                 MediaLayout::Compact => {
+                     let art = swipeable(if let Some(art) = self.art_handle() {
+                         image(art).width(Length::Fixed(COMPACT_ART_SIZE.into())).height(Length::Fixed(COMPACT_ART_SIZE.into())).into()
+                     } else {
+                         self.named_icon(AUDIO_GENERIC).size(COMPACT_ART_SIZE).into()
+                     });
+                     let info = column![].push_maybe(source).extend([
+                         copyable(self.title_label(&title)),
+                         text::caption(ellipsize(&artist, COMPACT_TEXT_CHARS)).into(),
+                     ]).push_maybe(album.map(|a| text::caption(ellipsize(&a, COMPACT_TEXT_CHARS)))).width(Length::Fill);
+                     let mut media_column = column![
+                         row![art, info, controls_row].spacing(12).align_y(Alignment::Center),
+                     ].spacing(8).width(Length::Fill);
+                     if let Some(progress) = progress { media_column = media_column.push(progress); }
+                     media_column.into()
+                 }
              };
-             controls.push(media_btn(icon_name, Message::MprisRequest(action)));
-             if s.can_go_next { controls.push(media_btn(GO_NEXT, Message::MprisRequest(MprisRequest::Next))); }
-             let controls_row = Row::with_children(controls).spacing(16).align_y(Alignment::Center);
-             
-             // ROW 3: TITLE
-             let title_text = text::body(s.title.clone().unwrap_or_default());
-
-             // ROW 4: ARTIST
-             let artist_text = text::caption(s.artists.as_ref().map(|a| a.join(", ")).unwrap_or_else(|| fl!("unknown-artist")));
-
-             let media_column = column![
-                 art,
-                 controls_row,
-                 title_text,
-                 artist_text
-             ].spacing(12).align_x(Alignment::Center).width(Length::Fill);
              
-             content = content.push(padded_control(media_column));
+             content = content.push(padded_control(media));
+        } else if self.config.media_placeholder {
+            // Stands in for the media section so the popup keeps its height when a player starts
+            let (go_back, go_next, play) = if self.rtl { (GO_BACK_RTL, GO_NEXT_RTL, PLAY_RTL) } else { (GO_BACK, GO_NEXT, PLAY) };
+            let controls = self.mirrored(vec![
+                self.media_btn(self.named_icon(go_back), fl!("previous-track"), None::<Message>, false),
+                self.media_btn(self.named_icon(play), fl!("play"), None::<Message>, false),
+                self.media_btn(self.named_icon(go_next), fl!("next-track"), None::<Message>, false),
+            ]).spacing(16).align_y(Alignment::Center);
+            content = content.push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]))
+                .push(padded_control(column![
+                    controls,
+                    text::caption(fl!("no-media-playing")).width(Length::Fill).align_x(Alignment::Center),
+                ].spacing(12).align_x(Alignment::Center).width(Length::Fill)));
+        }
+
+        if let Some((notice, _)) = self.notice.as_ref() {
+            content = content.push(padded_control(text::caption(notice.clone()).width(Length::Fill).align_x(self.start())));
+        }
+
+        if let Some(undo) = self.undo.as_ref() {
+            content = content.push(padded_control(self.mirrored(vec![
+                text::caption(fl!("device-switched", device = ellipsize(&self.display_name(&undo.current), MEDIA_TEXT_CHARS))).width(Length::Fill).align_x(self.start()).into(),
+                button::text(fl!("undo")).on_press(Message::UndoDeviceSwitch).into(),
+            ]).spacing(8).align_y(Alignment::Center)));
         }
 
         // --- 4. FOOTER ---
         content = content.push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]))
-             .push(menu_button(text::body(fl!("sound-settings"))).on_press(Message::OpenSettings));
+             .push(row![
+                 menu_button(text::body(fl!("sound-settings"))).on_press(Message::OpenSettings).width(Length::Fill),
+                 container(labelled(button::icon(self.named_icon(REFRESH).size(self.icon_size(16))), fl!("refresh-devices"))
+                     .class(cosmic::theme::Button::Icon)
+                     .on_press_maybe(self.refresh_cooldown.is_none().then_some(Message::RefreshDevices)))
+                     .padding([0, space_s]),
+             ].align_y(Alignment::Center));
 
-        self.core.applet.popup_container(container(content.align_x(Alignment::Start).padding([8, 0]))).into()
+        content
+    }
+
+    /// Mute button, slider and percentage label, plus a unity-gain marker when amplified.
+    fn volume_row<'a>(&'a self, c: VolumeControl<'a>) -> Element<'a, Message> {
+        let label = text(localize::percent(c.volume)).size(16);
+        // Flag boosted levels, which may clip
+        let label = if c.volume > 100 { label.class(cosmic::theme::Text::Color(theme::active().cosmic().warning_color().into())) } else { label };
+        // Clicking the level turns it into a field for typing an exact value
+        let label: Element<'a, Message> = match c.entry {
+            Some(entry) => cosmic::widget::text_input("", entry).id(c.entry_id.clone())
+                .on_input(c.on_edit).on_submit(c.on_submit).on_unfocus((c.on_submit)(entry.to_owned())).into(),
+            None => button::custom(label).class(cosmic::theme::Button::Text).padding(0).on_press((c.on_edit)(c.volume.to_string())).into(),
+        };
+        let on_set = c.on_set;
+        let (volume, limit) = (c.volume, c.limit);
+        let step_button = |name: &'static str, label: String, msg: Option<Message>| -> Element<'a, Message> {
+            labelled(button::icon(self.named_icon(name).size(self.icon_size(16))), label)
+                .class(cosmic::theme::Button::Icon).on_press_maybe(msg).into()
+        };
+        let has_extra = c.extra.is_some();
+        let mut controls = vec![
+            labelled(button::icon(self.named_icon(c.icon).size(self.icon_size(24))), c.mute_label)
+                .class(self.mute_button_class(c.muted)).on_press(c.on_mute).into(),
+        ];
+        controls.extend(c.extra);
+        controls.extend([
+            step_button("list-remove-symbolic", fl!("volume-down"), (volume > 0).then(|| on_set(self.stepped_volume(volume, -1.0, volume)))),
+            slider(0..=c.max, c.volume, c.on_drag)
+                .width(Length::FillPortion(5)).breakpoints(c.breakpoints)
+                .on_release(c.on_release).into(),
+            step_button("list-add-symbolic", fl!("volume-up"), (volume < limit).then(|| on_set(self.stepped_volume(volume, 1.0, limit)))),
+            container(label).width(Length::FillPortion(1)).align_x(self.end()).into()
+        ]);
+        let row = padded_control(self.mirrored(controls).spacing(12).align_y(Alignment::Center));
+        // Scrolling anywhere on the row, the slider included, moves by the configured step up to `limit`
+        let row = crate::mouse_area::MouseArea::new(row).intercept()
+            .on_mouse_wheel(move |delta| on_set(self.scrolled_volume(volume, limit, delta)));
+
+        if c.max <= 100 { return row.into(); }
+        // A warning band under the boosted part of the slider, and "100%" at the unity-gain
+        // breakpoint where it starts, laid out like the row above
+        let mut spacers = vec![iced::widget::Space::with_width(Length::Fixed(MUTE_BUTTON_WIDTH)).into()];
+        if has_extra { spacers.push(iced::widget::Space::with_width(Length::Fixed(STEP_BUTTON_WIDTH)).into()); }
+        spacers.extend([
+            iced::widget::Space::with_width(Length::Fixed(STEP_BUTTON_WIDTH)).into(),
+            column![
+                self.mirrored(vec![
+                    iced::widget::Space::with_width(Length::FillPortion(100)).into(),
+                    container(boost_band()).width(Length::FillPortion((c.max - 100) as u16)).into(),
+                ]),
+                self.mirrored(vec![
+                    iced::widget::Space::with_width(Length::FillPortion(100)).into(),
+                    text::caption(localize::percent(100)).into(),
+                    iced::widget::Space::with_width(Length::FillPortion((c.max - 100) as u16)).into(),
+                ]),
+            ].spacing(2).width(Length::FillPortion(5)).into(),
+            iced::widget::Space::with_width(Length::Fixed(STEP_BUTTON_WIDTH)).into(),
+            iced::widget::Space::with_width(Length::FillPortion(1)).into(),
+        ]);
+        column![row, padded_control(self.mirrored(spacers).spacing(12))].into()
+    }
+
+    /// The input's level, with toggles for the channels it follows on multi-channel inputs.
+    fn input_meter_row(&self) -> Element<'_, Message> {
+        let follows = |i: usize| self.config.input_meter_channels.is_empty() || self.config.input_meter_channels.contains(&(i as u8));
+        let meter = |peak: f32| -> Element<'_, Message> { progress_bar(0.0..=1.0, peak).height(Length::Fixed(4.0)).into() };
+        let followed = self.input_peaks.iter().enumerate().filter(|(i, _)| follows(*i)).map(|(_, p)| *p);
+        let bars: Vec<Element<'_, Message>> = match self.config.input_meter_layout {
+            MeterLayout::Combined => vec![meter(followed.fold(0.0, f32::max))],
+            MeterLayout::PerChannel => followed.map(meter).collect(),
+        };
+        let mut content = column![].extend(bars).spacing(4);
+        if self.input_peaks.len() > 1 {
+            let mut toggles: Vec<Element<'_, Message>> = (0..self.input_peaks.len()).map(|i| {
+                let class = if follows(i) { cosmic::theme::Button::Suggested } else { cosmic::theme::Button::Standard };
+                button::text((i + 1).to_string()).class(class).on_press(Message::ToggleMeterChannel(i as u8)).into()
+            }).collect();
+            let layout = match self.config.input_meter_layout { MeterLayout::Combined => fl!("meter-per-channel"), MeterLayout::PerChannel => fl!("meter-combined") };
+            toggles.extend([horizontal_space().into(), button::text(layout).on_press(Message::ToggleMeterLayout).into()]);
+            content = content.push(self.mirrored(toggles).spacing(4).align_y(Alignment::Center));
+        }
+        padded_control(content).into()
+    }
+
+    /// Toggles the input between the configured boost level and its previous level.
+    fn mic_boost_button(&self) -> Element<'_, Message> {
+        let (label, class) = match self.pre_boost_volume {
+            Some(_) => (fl!("mic-boost-off"), cosmic::theme::Button::Suggested),
+            None => (fl!("mic-boost"), cosmic::theme::Button::Icon),
+        };
+        labelled(button::icon(self.named_icon("microphone-sensitivity-high-symbolic").size(self.icon_size(16))), label)
+            .class(class).on_press(Message::ToggleMicBoost).into()
+    }
+
+    /// A full-popup device list with a back button, replacing the main content.
+    fn device_page(&self, title: String, active: Option<String>, devs: Vec<DeviceEntry>) -> cosmic::iced::widget::Column<'_, Message, Theme, Renderer> {
+        let Spacing { space_xxs, space_s, .. } = theme::active().cosmic().spacing;
+        let back = if self.rtl { "go-next-symbolic" } else { "go-previous-symbolic" };
+        let header = padded_control(self.mirrored(vec![
+            labelled(button::icon(self.named_icon(back).size(self.icon_size(16))), fl!("back")).class(cosmic::theme::Button::Icon).on_press(Message::ShowMain).into(),
+            text::heading(title).width(Length::Fill).align_x(self.start()).into(),
+        ]).spacing(8).align_y(Alignment::Center));
+        let empty = devs.is_empty().then(|| padded_control(text::caption(self.empty_list_label())));
+        let list = devs.into_iter().fold(column![].width(Length::Fill).push_maybe(empty), |c, entry| {
+            let selected = active.as_ref() == Some(&entry.name);
+            let check: Element<'_, Message> = if selected { self.named_icon("object-select-symbolic").size(16).into() } else { iced::widget::Space::with_width(Length::Fixed(16.0)).into() };
+            let on_press = entry.on_press.clone();
+            c.push(sliding(menu_button(self.mirrored(vec![device_row(&entry, self.max_sink_volume, self.rtl).into(), check]).spacing(8).align_y(Alignment::Center)).on_press(on_press).width(Length::Fill), entry.reveal))
+        });
+        column![
+            header,
+            padded_control(divider::horizontal::default()).padding([space_xxs, space_s]),
+            cosmic::widget::scrollable(list).height(Length::Shrink),
+        ].width(Length::Fill)
+    }
+
+    fn active_sink_name(&self) -> Option<&String> {
+        self.model.active_sink().and_then(|i| self.model.sinks().get(i))
+    }
+
+    fn active_source_name(&self) -> Option<&String> {
+        self.model.active_source().and_then(|i| self.model.sources().get(i))
+    }
+
+    /// Make `name` the default output; `None` when it is no longer listed.
+    fn select_sink(&mut self, name: &str) -> Option<Task<cosmic::Action<Message>>> {
+        let Some(idx) = self.model.sinks().iter().position(|n| n == name) else { tracing::warn!("Sink {name} is no longer available"); return None; };
+        self.requested_sink = Some(name.to_owned());
+        Some(self.model.set_default_sink(idx).map(|m| cosmic::Action::from(Message::Subscription(m))))
+    }
+
+    fn select_source(&mut self, name: &str) -> Option<Task<cosmic::Action<Message>>> {
+        let Some(idx) = self.model.sources().iter().position(|n| n == name) else { tracing::warn!("Source {name} is no longer available"); return None; };
+        self.requested_source = Some(name.to_owned());
+        Some(self.model.set_default_source(idx).map(|m| cosmic::Action::from(Message::Subscription(m))))
+    }
+
+    /// Whether the default `kind` device just changed by something other than this applet.
+    /// Only worth highlighting while the popup shows it and there was a previous default.
+    fn external_default_change(&mut self, kind: DeviceKind, had_default: bool) -> bool {
+        let (requested, active) = match kind {
+            DeviceKind::Sink => (&mut self.requested_sink, self.model.active_sink().and_then(|i| self.model.sinks().get(i))),
+            DeviceKind::Source => (&mut self.requested_source, self.model.active_source().and_then(|i| self.model.sources().get(i))),
+        };
+        if active.is_some() && requested.as_ref() == active {
+            *requested = None;
+            return false;
+        }
+        had_default && active.is_some() && self.popup.is_some()
+    }
+
+    /// The active device's name under a revealer header, fading from the accent color after an external change.
+    fn selection_caption(&self, kind: DeviceKind, name: String) -> Element<'static, Message> {
+        let caption = text::caption(name);
+        let Some((_, pulse)) = self.default_pulse.filter(|(k, _)| *k == kind) else { return caption.into() };
+        let active = theme::active();
+        let (accent, normal) = (active.cosmic().accent_color().into(), active.cosmic().background.on.into());
+        caption.class(cosmic::theme::Text::Color(mix(accent, normal, pulse.at(Instant::now())))).into()
+    }
+
+    /// Keep only the most recent switch, replacing any older undo.
+    fn remember_switch(&mut self, kind: DeviceKind, previous: Option<String>, current: &str) -> Task<cosmic::Action<Message>> {
+        let Some(previous) = previous.filter(|p| p != current) else { return Task::none() };
+        self.undo = Some(UndoSwitch { kind, previous, current: current.to_owned(), expires: anim::Progress::new(UNDO_TIMEOUT) });
+        after(UNDO_TIMEOUT, Timer::Undo)
+    }
+
+    fn sink_entries(&self) -> Vec<DeviceEntry> {
+        let sinks = self.model.sinks();
+        let mut entries: Vec<DeviceEntry> = self.ordered(sinks.to_vec()).iter()
+            .map(|n| DeviceEntry {
+                name: n.clone(),
+                label: self.config.device_labels.get(n).cloned(),
+                editing: self.label_editing(n),
+                detail: self.config.device_volumes.get(n).map(|v| localize::percent(*v)),
+                volume: self.sink_nodes.iter().find(|node| node.name == *n).filter(|_| self.active_sink_name() != Some(n)).map(|node| node.volume),
+                status_icon: match self.combine.as_ref() {
+                    Some(chosen) => Some(self.named_icon(if chosen.contains(n) { "checkbox-checked-symbolic" } else { "checkbox-symbolic" })),
+                    None => self.bluetooth_icon(n),
+                },
+                on_press: if self.combine.is_some() { Message::ToggleCombined(n.clone()) } else { Message::SetDefaultSink(n.clone()) },
+                reveal: 1.0,
+            })
+            .collect();
+        // Paired Bluetooth devices have no sink until connected; offer to connect them
+        entries.extend(self.bt_devices.iter()
+            .filter(|d| !d.connected && !sinks.iter().any(|n| self.bt_addresses.get(n) == Some(&d.address)))
+            .map(|d| DeviceEntry {
+                name: d.name.clone(),
+                label: self.config.device_labels.get(&d.name).cloned(),
+                editing: self.label_editing(&d.name),
+                detail: None,
+                volume: None,
+                status_icon: Some(self.named_icon(BT_DISCONNECTED)),
+                on_press: Message::ConnectBluetooth(d.address.clone()),
+                reveal: 1.0,
+            }));
+        self.with_hotplug(DeviceKind::Sink, entries)
+    }
+
+    /// Start sliding in the rows of devices in `after` only, and out those in `before` only.
+    fn track_hotplug(&mut self, kind: DeviceKind, mut before: Vec<String>, after: Vec<String>) {
+        if kind == DeviceKind::Source { before.retain(|n| self.source_listed(n)); }
+        for name in after.iter().filter(|n| !before.contains(n)) {
+            self.hotplug.retain(|h| h.kind != kind || h.name != *name);
+            self.hotplug.push(Hotplug { kind, name: name.clone(), removed_at: None, progress: anim::Progress::new(HOTPLUG_DURATION) });
+        }
+        let before = self.ordered(before);
+        for (index, name) in before.iter().enumerate().filter(|(_, n)| !after.contains(n)) {
+            self.hotplug.retain(|h| h.kind != kind || h.name != *name);
+            self.hotplug.push(Hotplug { kind, name: name.clone(), removed_at: Some(index), progress: anim::Progress::new(HOTPLUG_DURATION) });
+        }
+    }
+
+    /// `entries` with sliding rows sized, and rows of just removed devices put back while they slide out.
+    fn with_hotplug(&self, kind: DeviceKind, mut entries: Vec<DeviceEntry>) -> Vec<DeviceEntry> {
+        let now = Instant::now();
+        for h in self.hotplug.iter().filter(|h| h.kind == kind) {
+            let reveal = h.progress.at(now);
+            match h.removed_at {
+                None => if let Some(entry) = entries.iter_mut().find(|e| e.name == h.name) { entry.reveal = reveal; },
+                Some(index) => entries.insert(index.min(entries.len()), DeviceEntry {
+                    name: h.name.clone(), label: self.config.device_labels.get(&h.name).cloned(), editing: None, detail: None,
+                    volume: None, status_icon: None, on_press: Message::Ignore, reveal: 1.0 - reveal,
+                }),
+            }
+        }
+        entries
+    }
+
+    /// The rename field's text when `name` is being renamed.
+    fn label_editing(&self, name: &str) -> Option<String> {
+        self.label_entry.as_ref().filter(|(n, _)| n == name).map(|(_, text)| text.clone())
+    }
+
+    /// The user's label for a device, or its own name.
+    fn display_name(&self, name: &str) -> String {
+        self.config.device_labels.get(name).cloned().unwrap_or_else(|| name.to_owned())
+    }
+
+    fn source_entries(&self) -> Vec<DeviceEntry> {
+        let entries = self.visible_sources().into_iter()
+            .map(|n| DeviceEntry { label: self.config.device_labels.get(&n).cloned(), editing: self.label_editing(&n), detail: None, volume: None, status_icon: self.bluetooth_icon(&n), on_press: Message::SetDefaultSource(n.clone()), reveal: 1.0, name: n })
+            .collect();
+        self.with_hotplug(DeviceKind::Source, entries)
+    }
+
+    /// Battery level of the active output when it is a wireless headset, matched by
+    /// Bluetooth address or else by the exact model name.
+    fn active_sink_battery(&self) -> Option<u8> {
+        let sink = self.active_sink_name()?;
+        let address = self.bt_addresses.get(sink);
+        self.batteries.iter()
+            .find(|b| match (address, b.address.as_ref()) {
+                (Some(address), Some(battery)) => address == battery,
+                _ => b.model == *sink,
+            })
+            .map(|b| b.percentage)
+    }
+
+    fn bluetooth_icon(&self, name: &str) -> Option<icon::Named> {
+        let address = self.bt_addresses.get(name)?;
+        self.bt_devices.iter().any(|d| d.connected && d.address == *address).then(|| self.named_icon(BT_CONNECTED))
+    }
+
+    /// Make the awaited output default once its sink is listed.
+    fn apply_pending_default(&mut self) -> Task<cosmic::Action<Message>> {
+        let Some(pending) = self.pending_default.as_ref() else { return Task::none() };
+        let found = self.model.sinks().iter().position(|n| match pending {
+            PendingDefault::Named(name) => n == name,
+            PendingDefault::Bluetooth(address) => self.bt_addresses.get(n) == Some(address),
+        });
+        let Some(idx) = found else { return Task::none() };
+        self.pending_default = None;
+        self.requested_sink = self.model.sinks().get(idx).cloned();
+        self.model.set_default_sink(idx).map(|m| cosmic::Action::from(Message::Subscription(m)))
+    }
+
+    fn remember_sink_volume(&mut self, val: u32) {
+        // That level belongs to the pinned output or an app, not the default output
+        if self.main_node().is_some() { return; }
+        let Some(name) = self.active_sink_name().cloned() else { return };
+        if self.config.device_volumes.get(&name) == Some(&val) { return; }
+        let mut volumes = self.config.device_volumes.clone();
+        volumes.insert(name, val);
+        let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+            .and_then(|config| self.config.set_device_volumes(&config, volumes));
+        if let Err(err) = result { tracing::error!("Failed to save device volume: {err:?}"); }
+    }
+
+    /// Starts choosing outputs to combine, then plays on the chosen ones.
+    fn combine_row(&self) -> Element<'static, Message> {
+        let row = match self.combine.as_ref() {
+            None => vec![button::text(fl!("combine-outputs")).on_press(Message::ChooseCombined).into()],
+            Some(chosen) => vec![
+                button::text(fl!("cancel")).on_press(Message::ChooseCombined).into(),
+                button::suggested(fl!("play-on-selected"))
+                    .on_press_maybe((chosen.len() >= 2).then(|| Message::CreateCombinedSink(chosen.clone()))).into(),
+            ],
+        };
+        padded_control(self.mirrored(row).spacing(8)).into()
+    }
+
+    fn settings_button(&self, page: SoundPage) -> Element<'static, Message> {
+        let label = match page { SoundPage::Output => fl!("output-settings"), SoundPage::Input => fl!("input-settings") };
+        labelled(button::icon(self.named_icon(SETTINGS).size(self.icon_size(16))), label)
+            .class(cosmic::theme::Button::Icon).on_press(Message::OpenSettings).into()
+    }
+
+    fn close_device_list(&mut self, section: IsOpen) {
+        if self.config.active_device_click == ActiveDeviceClick::Nothing { return; }
+        if self.view_mode != ViewMode::Main {
+            self.view_mode = ViewMode::Main;
+        } else if self.is_open == section {
+            self.toggle_revealer(section);
+        }
+    }
+
+    fn toggle_revealer(&mut self, section: IsOpen) {
+        let closing = self.is_open;
+        self.is_open = if closing == section { IsOpen::None } else { section };
+        self.reveal_anim = Some(RevealAnim { opening: self.is_open, closing, progress: anim::Progress::new(REVEAL_DURATION) });
+    }
+
+    /// How far `section`'s device list is expanded, from 0.0 (collapsed) to 1.0 (open).
+    fn reveal_fraction(&self, section: IsOpen) -> f32 {
+        match self.reveal_anim {
+            Some(a) if a.opening == section && section != IsOpen::None => a.progress.at(Instant::now()),
+            Some(a) if a.closing == section && section != IsOpen::None => 1.0 - a.progress.at(Instant::now()),
+            _ => if self.is_open == section { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// A media control button; it is disabled when `msg` is `None` and highlighted when `active`.
+    fn media_btn(&self, icon: icon::Named, label: String, msg: impl Into<Option<Message>>, active: bool) -> Element<'static, Message> {
+        let class = if active { self.art_accent().map_or(cosmic::theme::Button::Suggested, accent_button) } else { cosmic::theme::Button::AppletIcon };
+        labelled(button::icon(icon.size(32)).extra_small(), label).class(class).on_press_maybe(msg.into()).into()
+    }
+
+    /// The current art's color, when `art_accent` is on and it has been picked.
+    fn art_accent(&self) -> Option<iced::Color> {
+        let (path, color) = self.art_accent.as_ref().filter(|_| self.config.art_accent)?;
+        (self.player_status.as_ref()?.icon.as_ref() == Some(path)).then_some(*color)?
+    }
+
+    /// Keep the player's reported state to fall back to, until it confirms the change
+    /// about to be shown or `PLAYER_CONFIRM_TIMEOUT` passes.
+    fn expect_player(&mut self) -> Task<cosmic::Action<Message>> {
+        let Some(s) = self.player_status.as_ref() else { return Task::none() };
+        // Further presses before an update still fall back to the last real state
+        let reported = self.reported_player.take().map_or_else(|| s.clone(), |(r, _)| r);
+        self.reported_player = Some((reported, anim::Progress::new(PLAYER_CONFIRM_TIMEOUT)));
+        after(PLAYER_CONFIRM_TIMEOUT, Timer::PlayerConfirm)
+    }
+
+    fn set_playback(&mut self, status: PlaybackStatus) {
+        if let Some(s) = self.player_status.as_mut() { s.status = status; }
+    }
+
+    /// Whether a media button sending `req` was just pressed.
+    fn media_pressed(&self, req: &MprisRequest) -> bool {
+        self.pressed_media.is_some_and(|(d, _)| d == std::mem::discriminant(req))
+    }
+
+    fn close_popup(&mut self) -> Task<cosmic::Action<Message>> {
+        let Some(p) = self.popup.take() else { return Task::none(); };
+        self.reset_popup_state();
+        destroy_popup(p)
+    }
+
+    /// Follow a device selection with closing the popup when `close_on_select` is set.
+    fn then_close_if_configured(&mut self, task: Task<cosmic::Action<Message>>) -> Task<cosmic::Action<Message>> {
+        if !self.config.close_on_select { return task; }
+        task.chain(self.close_popup())
+    }
+
+    /// Drop state that only lives while the popup is shown, including uncommitted
+    /// drag values, so a reopened popup starts fresh.
+    fn reset_popup_state(&mut self) {
+        self.is_open = IsOpen::None;
+        self.view_mode = ViewMode::Main;
+        self.combine = None;
+        self.boost_prompt = None;
+        self.fine_anchor = None;
+        self.label_entry = None;
+        self.sink_drag_val = None;
+        self.source_drag_val = None;
+        self.sink_entry = None;
+        self.source_entry = None;
+        self.reveal_anim = None;
+        self.sink_anim = None;
+        self.source_anim = None;
+        self.hotplug.clear();
+        self.clipping = None;
+        (self.input_channels, self.input_peaks) = (None, Vec::new());
+        self.hover.popup = false;
+    }
+
+    fn media_text_chars(&self) -> usize {
+        match self.config.media_layout { MediaLayout::Vertical => MEDIA_TEXT_CHARS, MediaLayout::Compact => COMPACT_TEXT_CHARS }
+    }
+
+    /// Whether the popup shows a title long enough to need scrolling.
+    fn marquee_active(&self) -> bool {
+        self.config.marquee_titles && self.popup.is_some()
+            && self.player_status.as_ref().and_then(|s| s.title.as_ref()).is_some_and(|t| t.chars().count() > self.media_text_chars())
+    }
+
+    fn title_text(&self, title: &str) -> String {
+        let width = self.media_text_chars();
+        match self.marquee_start {
+            Some(start) if self.config.marquee_titles => marquee(title, width, start.elapsed()),
+            _ => ellipsize(title, width),
+        }
+    }
+
+    /// The title, fading in from the accent color just after the track changed.
+    fn title_label(&self, title: &str) -> cosmic::iced::widget::Text<'static, Theme, Renderer> {
+        let label = text::body(self.title_text(title));
+        let Some(flash) = self.track_flash else { return label };
+        let active = theme::active();
+        let (accent, normal) = (active.cosmic().accent_color().into(), active.cosmic().background.on.into());
+        label.class(cosmic::theme::Text::Color(mix(accent, normal, flash.at(Instant::now()))))
+    }
+
+    fn visible_sources(&self) -> Vec<String> {
+        self.ordered(self.model.sources().iter()
+            .filter(|name| self.source_listed(name))
+            .cloned()
+            .collect())
+    }
+
+    fn source_listed(&self, name: &str) -> bool {
+        self.config.show_monitor_sources || !self.monitor_sources.iter().any(|m| m == name)
+    }
+
+    /// Device names in `device_sort` order, with pinned devices first in the order they were pinned.
+    fn ordered(&self, mut names: Vec<String>) -> Vec<String> {
+        match self.config.device_sort {
+            DeviceSort::Model => {}
+            DeviceSort::Alphabetical => names.sort_by_key(|n| self.display_name(n).to_lowercase()),
+            DeviceSort::LastUsed => names.sort_by_key(|n| self.config.recent_devices.iter().position(|r| r == n).unwrap_or(usize::MAX)),
+        }
+        // Stable, so the chosen order holds among pinned and unpinned devices alike
+        names.sort_by_key(|n| self.config.pinned_devices.iter().position(|p| p == n).unwrap_or(usize::MAX));
+        names
+    }
+
+    /// Move `name` to the front of the recently used devices.
+    fn remember_recent(&mut self, name: &str) {
+        if self.config.recent_devices.first().is_some_and(|r| r == name) { return; }
+        let mut recent = self.config.recent_devices.clone();
+        recent.retain(|r| r != name);
+        recent.insert(0, name.to_owned());
+        recent.truncate(MAX_RECENT_DEVICES);
+        let result = cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
+            .and_then(|config| self.config.set_recent_devices(&config, recent));
+        if let Err(err) = result { tracing::error!("Failed to save recent devices: {err:?}"); }
+    }
+
+    /// Recompute slider maxima and breakpoints from amplification settings and the night cap.
+    fn update_limits(&mut self) {
+        (self.max_sink_volume, self.sink_breakpoints) = if amplification_sink() { (150, vec![100]) } else { (100, vec![]) };
+        (self.max_source_volume, self.source_breakpoints) = if amplification_source() { (150, vec![100]) } else { (100, vec![]) };
+        // Custom maxima win over amplification either way, keeping the 100% mark when above it
+        if let Some(max) = self.config.max_output_volume {
+            (self.max_sink_volume, self.sink_breakpoints) = (max, if max > 100 { vec![100] } else { vec![] });
+        }
+        if let Some(max) = self.config.max_input_volume {
+            (self.max_source_volume, self.source_breakpoints) = (max, if max > 100 { vec![100] } else { vec![] });
+        }
+        // Mark the cap on the slider so its ceiling is visible
+        if let Some(cap) = self.volume_cap().filter(|cap| *cap < self.max_sink_volume && !self.sink_breakpoints.contains(cap)) {
+            self.sink_breakpoints.push(cap);
+            self.sink_breakpoints.sort_unstable();
+        }
+    }
+
+    /// Forget drag values far from a volume the subscription just reported, so a lost
+    /// CommitSink/CommitSource must not pin the slider to an old drag value.
+    fn drop_stale_drags(&mut self, sink_before: u32, source_before: u32) {
+        if self.model.sink_volume != sink_before && self.main_node().is_none() && self.sink_drag_val.is_some_and(|v| v.abs_diff(self.model.sink_volume) > STALE_DRAG_THRESHOLD) {
+            self.sink_drag_val = None;
+        }
+        if self.model.source_volume != source_before && self.source_drag_val.is_some_and(|v| v.abs_diff(self.model.source_volume) > STALE_DRAG_THRESHOLD) {
+            self.source_drag_val = None;
+        }
+    }
+
+    /// Keep drags within the slider maxima, and bring volumes down to a maximum that was
+    /// just lowered from `previous` (output, input). Levels set above an unchanged
+    /// maximum elsewhere are left alone.
+    fn clamp_to_limits(&mut self, previous: (u32, u32)) -> Task<cosmic::Action<Message>> {
+        let (sink_max, source_max) = (self.max_sink_volume, self.max_source_volume);
+        self.sink_drag_val = self.sink_drag_val.map(|v| v.min(sink_max));
+        self.source_drag_val = self.source_drag_val.map(|v| v.min(source_max));
+        let (volume, muted) = self.main_sink();
+        if source_max < previous.1 && self.model.source_volume > source_max {
+            self.run_command(AudioCommand::SetVolume(Target::DefaultSource, source_max));
+        }
+        if sink_max < previous.0 && volume > sink_max {
+            self.apply_sink_volume(sink_max);
+            return self.expect_sink(sink_max, muted);
+        }
+        Task::none()
+    }
+
+    fn volume_cap(&self) -> Option<u32> {
+        self.config.night_cap.filter(|_| self.night_cap_active)
+    }
+
+    /// Clamp an output volume to the active night cap.
+    fn capped(&self, volume: u32) -> u32 {
+        self.volume_cap().map_or(volume, |cap| volume.min(cap))
+    }
+
+    /// Set the main slider's output to `val`. A level above zero ends a zero-volume mute,
+    /// so the remembered level must not be restored over it.
+    fn apply_sink_volume(&mut self, val: u32) {
+        self.run_command(AudioCommand::SetVolume(self.sink_target(), val));
+        if val > 0 { self.pre_mute_volume = None; }
+    }
+
+    /// Change a volume or mute state after any changes still running.
+    fn run_command(&self, command: AudioCommand) {
+        let command = match self.command_queue.as_ref().map(|queue| queue.unbounded_send(command)) {
+            Some(Ok(())) => return,
+            Some(Err(err)) => err.into_inner(),
+            // Before the queue is up there is nothing to wait for
+            None => command,
+        };
+        if let Err(err) = audio_command::spawn(command) { tracing::warn!("{command:?}: {err}"); }
+    }
+
+    /// The pinned output's node, while it is present.
+    fn pinned_node(&self) -> Option<&wpctl::Node> {
+        let name = self.config.pinned_sink.as_ref()?;
+        // Unplugged outputs leave the model before `sink_nodes` is read again
+        if !self.model.sinks().contains(name) { return None; }
+        self.sink_nodes.iter().find(|n| n.name == *name)
+    }
+
+    /// Show `volume` and `muted` right away instead of waiting for the subscription,
+    /// and check them against PipeWire once `CONFIRM_TIMEOUT` passes.
+    fn expect_sink(&mut self, volume: u32, muted: bool) -> Task<cosmic::Action<Message>> {
+        // Only the default sink is followed and can be checked
+        let focused = self.focused_stream().map(|n| n.id);
+        let pinned = self.config.pinned_sink.clone();
+        let node = match focused {
+            Some(id) => self.app_streams.iter_mut().map(|s| &mut s.node).find(|n| n.id == id),
+            None => pinned.and_then(|name| self.sink_nodes.iter_mut().find(|n| n.name == name)),
+        };
+        if let Some(node) = node {
+            (node.volume, node.muted) = (volume, muted);
+            return Task::none();
+        }
+        (self.model.sink_volume, self.model.sink_mute) = (volume, muted);
+        self.model.sink_volume_text = localize::percent(volume);
+        self.sink_confirm = Some(anim::Progress::new(CONFIRM_TIMEOUT));
+        after(CONFIRM_TIMEOUT, Timer::SinkConfirm)
+    }
+
+    /// Show `notice` in the popup for `NOTICE_TIMEOUT`.
+    fn show_notice(&mut self, notice: String) -> Task<cosmic::Action<Message>> {
+        self.notice = Some((notice, anim::Progress::new(NOTICE_TIMEOUT)));
+        after(NOTICE_TIMEOUT, Timer::Notice)
+    }
+
+    /// The drag target for slider position `val`, moving a quarter as far while Shift is held.
+    fn fine_drag(&mut self, kind: DeviceKind, val: u32) -> u32 {
+        if !self.modifiers.shift() {
+            self.fine_anchor = None;
+            return val;
+        }
+        if self.fine_anchor.is_some_and(|(k, ..)| k != kind) { self.fine_anchor = None; }
+        let (shown, max) = match kind {
+            DeviceKind::Sink => (self.sink_volume(), self.max_sink_volume),
+            DeviceKind::Source => (self.source_volume(), self.max_source_volume),
+        };
+        let (_, origin, start) = *self.fine_anchor.get_or_insert((kind, shown as i32, val as i32));
+        (origin + (val as i32 - start) / FINE_DRAG_DIVISOR).clamp(0, max as i32) as u32
+    }
+
+    /// `volume`, held at 100% while raising the output past it awaits confirmation.
+    fn guard_boost(&mut self, volume: u32) -> u32 {
+        // Scripted changes with the popup closed have nowhere to ask
+        if volume <= 100 || self.boost_confirmed || !self.config.confirm_boost || self.popup.is_none() || self.main_sink().0 > 100 {
+            return volume;
+        }
+        self.boost_prompt = Some(volume);
+        100
+    }
+
+    fn boost_prompt_row(&self) -> Element<'_, Message> {
+        padded_control(self.mirrored(vec![
+            text::caption(fl!("confirm-boost")).width(Length::Fill).align_x(self.start()).into(),
+            button::text(fl!("cancel")).on_press(Message::CancelBoost).into(),
+            button::suggested(fl!("allow-boost")).on_press(Message::ConfirmBoost).into(),
+        ]).spacing(8).align_y(Alignment::Center)).into()
+    }
+
+    // Shown until dismissed, since scrolling the panel icon is otherwise undiscoverable
+    fn scroll_tip_row(&self) -> Element<'_, Message> {
+        padded_control(self.mirrored(vec![
+            text::caption(fl!("scroll-tip")).width(Length::Fill).align_x(self.start()).into(),
+            button::text(fl!("dismiss")).on_press(Message::DismissScrollTip).into(),
+        ]).spacing(8).align_y(Alignment::Center)).into()
+    }
+
+    fn clipping_row(&self) -> Element<'_, Message> {
+        let warning = cosmic::theme::Text::Color(theme::active().cosmic().warning_color().into());
+        padded_control(self.mirrored(vec![
+            icon::from_name("dialog-warning-symbolic").size(16).into(),
+            text::caption(fl!("output-clipping")).class(warning).into(),
+        ]).spacing(8).align_y(Alignment::Center)).into()
+    }
+
+    /// `current` moved one `scroll_step` in the wheel's direction, within `0..=max`.
+    fn scrolled_volume(&self, current: u32, max: u32, delta: iced::mouse::ScrollDelta) -> u32 {
+        self.stepped_volume(current, self.scroll_y(delta), max)
+    }
+
+    /// `current` moved `steps` scroll steps, negative lowering it, within `0..=max`.
+    ///
+    /// On the cubic curve steps are even in cube-root space, which roughly matches
+    /// perceived loudness: fine near silence and coarser towards full volume.
+    fn stepped_volume(&self, current: u32, steps: f32, max: u32) -> u32 {
+        let step = steps * self.config.scroll_step as f32;
+        let target = match self.config.scroll_curve {
+            ScrollCurve::Linear => current as i32 + step as i32,
+            ScrollCurve::Cubic => {
+                let target = ((current as f32 / 100.0).cbrt() + step / 100.0).max(0.0).powi(3) * 100.0;
+                // Near silence a step can round to nothing, but it should still move
+                match target.round() as i32 {
+                    t if t == current as i32 && step != 0.0 => t + step.signum() as i32,
+                    t => t,
+                }
+            }
+        };
+        target.clamp(0, max as i32) as u32
+    }
+
+    /// Vertical scroll amount in lines, positive meaning "more" after `invert_scroll`.
+    fn scroll_y(&self, delta: iced::mouse::ScrollDelta) -> f32 {
+        if self.config.invert_scroll { -scroll_y(delta) } else { scroll_y(delta) }
+    }
+
+    /// Playback time covered by one pixel of the progress bar.
+    fn progress_pixel(&self, length: Duration) -> Duration {
+        length.div_f32(self.popup_width().unwrap_or(NATURAL_POPUP_WIDTH))
+    }
+
+    fn popup_width(&self) -> Option<f32> {
+        self.config.popup_width.map(|w| w.max(MIN_POPUP_WIDTH).into())
+    }
+
+    /// Physical pixels across the album art in the current layout; the vertical
+    /// layout's width is estimated from the popup width, less the margins.
+    fn art_pixels(&self) -> u32 {
+        let Spacing { space_s, .. } = theme::active().cosmic().spacing;
+        let logical = match self.config.media_layout {
+            MediaLayout::Vertical => self.popup_width().unwrap_or(NATURAL_POPUP_WIDTH) - 2.0 * (f32::from(self.art_margin()) + f32::from(space_s)),
+            MediaLayout::Compact => COMPACT_ART_SIZE.into(),
+        };
+        (logical * self.core.scale_factor()).round().max(1.0) as u32
+    }
+
+    /// Resample the current art when it or the pixels it covers changed.
+    fn scale_art(&self) -> Task<cosmic::Action<Message>> {
+        let Some(path) = self.player_status.as_ref().and_then(|s| s.icon.clone()) else { return Task::none(); };
+        let width = self.art_pixels();
+        if self.scaled_art.as_ref().is_some_and(|(p, w, _)| *p == path && *w == width) { return Task::none(); }
+        Task::perform(art::scaled(path.clone(), width), move |handle| cosmic::Action::from(Message::ArtScaled(path.clone(), width, handle)))
+    }
+
+    /// The resampled art once ready, else the file as is; `None` when it can't be decoded.
+    fn art_handle(&self) -> Option<cosmic::widget::image::Handle> {
+        let path = self.player_status.as_ref()?.icon.as_ref()?;
+        match self.scaled_art.as_ref().filter(|(p, _, _)| p == path) {
+            Some((_, _, handle)) => handle.clone(),
+            None => Some(cosmic::widget::image::Handle::from_path(path)),
+        }
+    }
+
+    /// Horizontal margin around the album art, scaled with the popup width.
+    fn art_margin(&self) -> u16 {
+        self.popup_width().map_or(24, |w| (24.0 * w / NATURAL_POPUP_WIDTH).round() as u16)
+    }
+
+    fn start(&self) -> Alignment {
+        if self.rtl { Alignment::End } else { Alignment::Start }
+    }
+
+    fn end(&self) -> Alignment {
+        if self.rtl { Alignment::Start } else { Alignment::End }
+    }
+
+    /// A row whose children run right-to-left in RTL locales.
+    fn mirrored<'a>(&self, children: Vec<Element<'a, Message>>) -> Row<'a, Message> {
+        mirror_row(children, self.rtl)
+    }
+
+    fn is_animating(&self) -> bool {
+        // Popup-only animations expire on the first frame after reopening, so they need no ticks while closed
+        let popup_anims = self.default_pulse.is_some() || !self.hotplug.is_empty() || self.track_flash.is_some();
+        self.reveal_anim.is_some() || self.sink_anim.is_some() || self.source_anim.is_some()
+            || (self.popup.is_some() && popup_anims)
+    }
+
+    /// Read again what follows sinks and streams: whether the output plays, the pinned
+    /// output's level and the apps' streams, as far as they're in use.
+    fn reread_sinks(&self) -> Task<cosmic::Action<Message>> {
+        let mut tasks = Vec::new();
+        if self.config.playing_indicator {
+            tasks.push(Task::perform(wpctl::default_sink_running(), |running| cosmic::Action::from(Message::SinkRunning(running))));
+        }
+        if self.config.pinned_sink.is_some() {
+            tasks.push(Task::perform(wpctl::sinks(), |nodes| cosmic::Action::from(Message::SinkNodes(nodes))));
+        }
+        if self.config.follow_focused_app {
+            tasks.push(Task::perform(wpctl::app_streams(), |streams| cosmic::Action::from(Message::AppStreams(streams))));
+        }
+        Task::batch(tasks)
+    }
+
+    /// The focused window's stream, while following it and it plays one.
+    fn focused_stream(&self) -> Option<&wpctl::Node> {
+        let app = self.focused_app.as_deref().filter(|_| self.config.follow_focused_app)?;
+        self.app_streams.iter().find(|s| s.belongs_to(app)).map(|s| &s.node)
+    }
+
+    /// The node the main slider controls instead of the default output, if any.
+    fn main_node(&self) -> Option<&wpctl::Node> {
+        self.focused_stream().or_else(|| self.pinned_node())
+    }
+
+    /// Where the main slider's changes go: the focused app's stream, the pinned output, else the default one.
+    fn sink_target(&self) -> Target {
+        self.main_node().map_or(Target::DefaultSink, |n| Target::Node(n.id))
+    }
+
+    /// Volume and mute state the main slider shows.
+    fn main_sink(&self) -> (u32, bool) {
+        self.main_node().map_or((self.model.sink_volume, self.model.sink_mute), |n| (n.volume, n.muted))
+    }
+
+    /// Show `volume` and `muted` on the focused app's stream right away, since its
+    /// streams are only read again when focus moves.
+    fn expect_stream(&mut self, volume: u32, muted: bool) {
+        let Some(id) = self.focused_stream().map(|n| n.id) else { return };
+        if let Some(node) = self.app_streams.iter_mut().map(|s| &mut s.node).find(|n| n.id == id) {
+            (node.volume, node.muted) = (volume, muted);
+        }
+    }
+
+    /// The sink volume as shown on the slider: the drag value, else the animated or model value.
+    fn sink_volume(&self) -> u32 {
+        self.sink_drag_val.unwrap_or_else(|| self.sink_anim.map_or(self.main_sink().0, |a| a.value(Instant::now()).round() as u32))
+    }
+
+    fn source_volume(&self) -> u32 {
+        self.source_drag_val.unwrap_or_else(|| self.source_anim.map_or(self.model.source_volume, |a| a.value(Instant::now()).round() as u32))
+    }
+
+    /// `name` is the symbolic icon; its full-color variant drops the suffix.
+    fn named_icon(&self, name: &str) -> icon::Named {
+        if self.config.symbolic_icons {
+            icon::from_name(name).symbolic(true)
+        } else {
+            icon::from_name(name.trim_end_matches("-symbolic"))
+        }
+    }
+
+    fn panel_icon_button<'a>(&self, name: &'a str) -> cosmic::widget::Button<'a, Message> {
+        if let Some(size) = self.config.panel_icon_size {
+            // A larger icon takes from the padding, so the panel keeps its thickness
+            let (width, height) = self.core.applet.suggested_size(self.config.symbolic_icons);
+            let extent = width.max(height) + 2 * self.core.applet.suggested_padding(self.config.symbolic_icons);
+            let size = size.min(extent);
+            return button::custom(self.named_icon(name).size(size).icon())
+                .padding((extent - size) / 2)
+                .class(cosmic::theme::Button::AppletIcon);
+        }
+        if self.config.symbolic_icons {
+            self.core.applet.icon_button(name)
+        } else {
+            self.core.applet.icon_button_from_handle(self.named_icon(name).handle())
+        }
+    }
+
+    fn high_contrast(&self) -> bool {
+        theme::active().cosmic().is_high_contrast
+    }
+
+    /// Thin symbolic strokes get lost in high contrast, so draw popup icons larger.
+    fn icon_size(&self, base: u16) -> u16 {
+        if self.high_contrast() { base + base / 4 } else { base }
+    }
+
+    // A filled background keeps the muted state visible when the icon alone reads poorly
+    fn mute_button_class(&self, muted: bool) -> cosmic::theme::Button {
+        if muted && self.high_contrast() { cosmic::theme::Button::Standard } else { cosmic::theme::Button::Icon }
+    }
+
+    fn no_device_label(&self) -> String {
+        if self.detecting.is_some() { fl!("detecting-devices") } else { fl!("no-device") }
+    }
+
+    fn empty_list_label(&self) -> String {
+        if self.detecting.is_some() { fl!("detecting-devices") } else { fl!("no-devices-found") }
+    }
+
+    fn sink_muted(&self) -> bool {
+        self.main_sink().1 || self.pre_mute_volume.is_some()
     }
-}
 
-impl Audio {
     fn output_icon_name(&self) -> &'static str {
-        let v = self.sink_drag_val.unwrap_or(self.model.sink_volume);
-        if self.model.sink_mute || v == 0 { "audio-volume-muted-symbolic" } else if v < 33 { "audio-volume-low-symbolic" } else if v < 66 { "audio-volume-medium-symbolic" } else { "audio-volume-high-symbolic" }
+        let (volume, muted) = self.main_sink();
+        let v = self.sink_drag_val.unwrap_or(volume);
+        if muted || v == 0 { "audio-volume-muted-symbolic" } else if v < 33 { "audio-volume-low-symbolic" } else if v < 66 { "audio-volume-medium-symbolic" } else { "audio-volume-high-symbolic" }
     }
     fn input_icon_name(&self) -> &'static str {
         let v = self.source_drag_val.unwrap_or(self.model.source_volume);
@@ -285,13 +2174,344 @@ impl Audio {
     }
 }
 
-fn revealer(open: bool, title: String, sel: String, devs: &[String], toggle: Message, mut change: impl FnMut(usize) -> Message + 'static) -> cosmic::iced::widget::Column<'static, Message, Theme, Renderer> {
-    let head = menu_button(column![text::body(title).width(Length::Fill), text::caption(sel)]).on_press(toggle);
-    if open { 
-        devs.iter().enumerate().fold(column![head].width(Length::Fill), |c, (i, n)| c.push(menu_button(text::body(n.clone())).on_press(change(i)).width(Length::Fill).padding([8, 48]))) 
-    } else { column![head] }
+/// The parts of a volume row that differ between output and input.
+struct VolumeControl<'a> {
+    icon: &'static str,
+    volume: u32,
+    max: u32,
+    /// Highest value scrolling may reach, e.g. under the night cap.
+    limit: u32,
+    breakpoints: &'a [u32],
+    on_mute: Message,
+    muted: bool,
+    /// Accessible name of the icon-only mute button.
+    mute_label: String,
+    /// A button placed after the mute button, e.g. the microphone boost.
+    extra: Option<Element<'a, Message>>,
+    on_drag: fn(u32) -> Message,
+    on_release: Message,
+    on_set: fn(u32) -> Message,
+    /// Text of the exact-volume field while it is open.
+    entry: Option<&'a str>,
+    entry_id: &'a cosmic::widget::Id,
+    on_edit: fn(String) -> Message,
+    on_submit: fn(String) -> Message,
 }
 
-fn media_btn(name: &'static str, msg: Message) -> Element<'static, Message> {
-    button::icon(icon::from_name(name).size(32).symbolic(true)).extra_small().class(cosmic::theme::Button::AppletIcon).on_press(msg).into()
+/// A typed volume, accepting an optional trailing percent sign.
+fn parse_volume(s: &str) -> Option<u32> {
+    s.trim().trim_end_matches('%').trim_end().parse().ok()
+}
+
+/// One row of a device list.
+struct DeviceEntry {
+    name: String,
+    /// A user-assigned name shown instead of `name`.
+    label: Option<String>,
+    /// Text of the rename field while it is open.
+    editing: Option<String>,
+    /// Secondary caption, e.g. the remembered volume.
+    detail: Option<String>,
+    /// The level of a non-default output, adjustable in place.
+    volume: Option<u32>,
+    /// Trailing state icon, e.g. the Bluetooth connection.
+    status_icon: Option<icon::Named>,
+    /// Selects the device by name rather than by its position, which can shift under hotplug.
+    on_press: Message,
+    /// Fraction of the row shown while it slides in or out after hotplug.
+    reveal: f32,
+}
+
+fn mirror_row<'a>(mut children: Vec<Element<'a, Message>>, rtl: bool) -> Row<'a, Message> {
+    if rtl { children.reverse(); }
+    Row::with_children(children)
+}
+
+/// `max` tops the inline volume slider, like the main output slider.
+fn device_row(entry: &DeviceEntry, max: u32, rtl: bool) -> Row<'static, Message> {
+    let align = if rtl { Alignment::End } else { Alignment::Start };
+    let device = entry.name.clone();
+    let volume = entry.volume.map(|v| slider(0..=max, v, move |v| Message::SetDeviceVolume(device.clone(), v))
+        .on_release(Message::CommitDeviceVolume(entry.name.clone())).width(Length::Fill));
+    let renamed = entry.name.clone();
+    let title: Element<'static, Message> = match entry.editing.as_deref() {
+        Some(text) => cosmic::widget::text_input(entry.name.clone(), text.to_owned()).id(LABEL_ENTRY_ID.clone())
+            .on_input(move |s| Message::EditDeviceLabel(renamed.clone(), s)).on_submit(Message::SetDeviceLabel)
+            .on_unfocus(Message::SetDeviceLabel(text.to_owned())).into(),
+        None => text::body(entry.label.clone().unwrap_or_else(|| entry.name.clone())).into(),
+    };
+    let name = column![title].push_maybe(entry.detail.clone().map(text::caption)).push_maybe(volume).width(Length::Fill).align_x(align);
+    let mut children: Vec<Element<'static, Message>> = vec![name.into()];
+    if let Some(icon) = entry.status_icon.clone() { children.push(icon.size(16).into()); }
+    if entry.editing.is_none() {
+        let rename = Message::EditDeviceLabel(entry.name.clone(), entry.label.clone().unwrap_or_default());
+        children.push(labelled(button::icon(icon::from_name("document-edit-symbolic").size(16)), fl!("rename-device"))
+            .class(cosmic::theme::Button::Icon).on_press(rename).into());
+    }
+    mirror_row(children, rtl).spacing(8).align_y(Alignment::Center)
+}
+
+/// Run a call on a media player in the background, logging its error.
+fn media_call<T, E: std::fmt::Display>(what: &'static str, call: impl Future<Output = Result<T, E>> + Send + 'static) -> Task<cosmic::Action<Message>>
+where T: Send + 'static, E: Send + 'static {
+    Task::perform(call, move |result| {
+        if let Err(err) = result { tracing::warn!("Failed to {what}: {err}"); }
+        cosmic::Action::from(Message::Ignore)
+    })
+}
+
+/// Deliver `TimerDone(timer)` once `duration` has passed.
+fn after(duration: Duration, timer: Timer) -> Task<cosmic::Action<Message>> {
+    Task::perform(tokio::time::sleep(duration), move |_| cosmic::Action::from(Message::TimerDone(timer)))
+}
+
+/// Refresh Bluetooth connection state, headset batteries and the output's format.
+fn fetch_peripherals() -> Task<cosmic::Action<Message>> {
+    Task::batch([
+        Task::perform(bluetooth::audio_devices(), |devices| cosmic::Action::from(Message::BluetoothDevices(devices))),
+        Task::perform(wpctl::bluetooth_addresses(), |addresses| cosmic::Action::from(Message::BluetoothAddresses(addresses))),
+        Task::perform(upower::audio_batteries(), |batteries| cosmic::Action::from(Message::Batteries(batteries))),
+        Task::perform(wpctl::sinks(), |nodes| cosmic::Action::from(Message::SinkNodes(nodes))),
+        Task::perform(wpctl::default_sink_format(), |format| cosmic::Action::from(Message::SinkFormat(format))),
+    ])
+}
+
+/// `detail` is an extra caption shown after the selected device, e.g. its battery level,
+/// and `max` the top of the devices' inline volume sliders.
+#[allow(clippy::too_many_arguments)]
+fn revealer(open: f32, title: String, sel: Element<'static, Message>, detail: Option<String>, devs: Vec<DeviceEntry>, max: u32, empty: String, settings: Element<'static, Message>, rtl: bool, toggle: Message) -> cosmic::iced::widget::Column<'static, Message, Theme, Renderer> {
+    let align = if rtl { Alignment::End } else { Alignment::Start };
+    let mut sel_row: Vec<Element<'static, Message>> = vec![sel];
+    if let Some(detail) = detail { sel_row.push(text::caption(detail).into()); }
+    sel_row.push(iced::widget::Space::with_width(Length::Fill).into());
+    let head = menu_button(column![text::body(title).width(Length::Fill).align_x(align), mirror_row(sel_row, rtl).spacing(8)]).on_press(toggle).width(Length::Fill);
+    let head = mirror_row(vec![head.into(), settings], rtl).align_y(Alignment::Center);
+    if open <= 0.0 { return column![head]; }
+    let count = devs.len().max(1);
+    let placeholder = devs.is_empty().then(|| container(text::caption(empty)).padding([8, 48]));
+    let list = devs.into_iter().fold(column![].width(Length::Fill).push_maybe(placeholder), |c, entry| {
+        let row = menu_button(device_row(&entry, max, rtl)).on_press(entry.on_press).width(Length::Fill).padding([8, 48]);
+        c.push(sliding(row, entry.reveal))
+    });
+    if open >= 1.0 {
+        column![head, list].width(Length::Fill)
+    } else {
+        // Clip the list to a growing/shrinking height while the transition runs
+        let height = open * count as f32 * DEVICE_ROW_HEIGHT;
+        column![head, container(list).height(Length::Fixed(height)).clip(true)].width(Length::Fill)
+    }
+}
+
+/// `row` clipped to `reveal` of a device row's height while it slides in or out.
+fn sliding<'a>(row: impl Into<Element<'a, Message>>, reveal: f32) -> Element<'a, Message> {
+    if reveal >= 1.0 { return row.into(); }
+    container(row).height(Length::Fixed(reveal * DEVICE_ROW_HEIGHT)).clip(true).into()
+}
+
+/// "Artist - Title" for the clipboard, or just the title when there is no artist.
+fn track_info(s: &mpris_subscription::PlayerStatus) -> Option<String> {
+    let title = s.title.as_deref().filter(|t| !t.is_empty())?;
+    Some(match s.artists.as_ref().map(|a| a.join(", ")).filter(|a| !a.is_empty()) {
+        Some(artist) => format!("{artist} - {title}"),
+        None => title.to_owned(),
+    })
+}
+
+/// The track title, copied by right-clicking it.
+fn copyable<'a>(title: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+    crate::mouse_area::MouseArea::new(title).on_right_press(Message::CopyTrackInfo).into()
+}
+
+/// Linear blend from `a` to `b` at `t` in `0.0..=1.0`.
+fn mix(a: iced::Color, b: iced::Color, t: f32) -> iced::Color {
+    iced::Color::from_rgba(a.r + (b.r - a.r) * t, a.g + (b.g - a.g) * t, a.b + (b.b - a.b) * t, a.a + (b.a - a.a) * t)
+}
+
+/// Shorten `s` to at most `max` characters, ending with "…" when cut.
+fn ellipsize(s: &str, max: usize) -> String {
+    if s.chars().count() <= max { return s.to_owned(); }
+    let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// The visible `width`-character window of `s`, bouncing between both ends with a pause at each.
+fn marquee(s: &str, width: usize, elapsed: Duration) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let overflow = chars.len().saturating_sub(width);
+    if overflow == 0 { return s.to_owned(); }
+    let period = 2 * (overflow + MARQUEE_PAUSE_STEPS);
+    let step = (elapsed.as_millis() / MARQUEE_STEP.as_millis()) as usize % period;
+    let offset = if step < MARQUEE_PAUSE_STEPS {
+        0
+    } else if step < MARQUEE_PAUSE_STEPS + overflow {
+        step - MARQUEE_PAUSE_STEPS
+    } else if step < 2 * MARQUEE_PAUSE_STEPS + overflow {
+        overflow
+    } else {
+        overflow - (step - 2 * MARQUEE_PAUSE_STEPS - overflow)
+    };
+    chars[offset..offset + width].iter().collect()
+}
+
+/// The current local hour, via `date` since std has no time zone support.
+async fn local_hour() -> Option<u8> {
+    let output = tokio::process::Command::new("date").arg("+%H").output().await.ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+fn scroll_y(delta: iced::mouse::ScrollDelta) -> f32 {
+    match delta { iced::mouse::ScrollDelta::Lines { y, .. } => y, iced::mouse::ScrollDelta::Pixels { y, .. } => y.signum() }
+}
+
+fn modifiers_changed(event: iced::Event, _status: iced::event::Status, _id: window::Id) -> Option<Message> {
+    match event {
+        iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers)) => Some(Message::Modifiers(modifiers)),
+        _ => None,
+    }
+}
+
+// The compositor ends a popup it refused to show (e.g. a denied grab) the same way as a
+// dismissed one, so this also catches `get_popup` failures that never fire `CloseRequested`
+fn popup_done(event: iced::Event, _status: iced::event::Status, _id: window::Id) -> Option<Message> {
+    use iced::event::{wayland, PlatformSpecific};
+    match event {
+        iced::Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Popup(wayland::PopupEvent::Done, _, id))) => Some(Message::CloseRequested(id)),
+        _ => None,
+    }
+}
+
+fn popup_key(event: iced::Event, status: iced::event::Status, id: window::Id) -> Option<Message> {
+    use iced::keyboard::{self, key::Named, Key};
+    let iced::Event::Keyboard(keyboard::Event::KeyPressed { key: Key::Named(key), modifiers, .. }) = event else { return None; };
+    if status == iced::event::Status::Captured { return None; }
+    let nav = match key {
+        Named::Tab if modifiers.shift() => KeyNav::FocusPrevious,
+        Named::Tab => KeyNav::FocusNext,
+        Named::ArrowUp => KeyNav::VolumeUp,
+        Named::ArrowDown => KeyNav::VolumeDown,
+        Named::ArrowLeft => KeyNav::SeekBackward,
+        Named::ArrowRight => KeyNav::SeekForward,
+        Named::Space => KeyNav::PlayPause,
+        Named::Escape => KeyNav::Back,
+        _ => return None,
+    };
+    Some(Message::Key(id, nav))
+}
+
+/// An icon-only button with an accessible name for screen readers.
+fn labelled<'a>(button: impl Into<cosmic::widget::Button<'a, Message>>, label: String) -> cosmic::widget::Button<'a, Message> {
+    button.into().name(label)
+}
+
+/// A small accent dot for the panel icon's corner.
+fn playing_dot() -> Element<'static, Message> {
+    let dot = container(iced::widget::Space::new(Length::Fixed(6.0), Length::Fixed(6.0))).class(cosmic::theme::Container::custom(|theme: &Theme| {
+        iced::widget::container::Style {
+            background: Some(iced::Color::from(theme.cosmic().accent_color()).into()),
+            border: iced::Border { radius: 3.0.into(), ..Default::default() },
+            ..Default::default()
+        }
+    }));
+    container(dot).width(Length::Fill).height(Length::Fill).align_x(Alignment::End).align_y(Alignment::End).padding(2).into()
+}
+
+/// A thin strip in the warning color, marking the volumes above 100% that may clip.
+fn boost_band() -> Element<'static, Message> {
+    container(iced::widget::Space::new(Length::Fill, Length::Fixed(3.0))).class(cosmic::theme::Container::custom(|theme: &Theme| {
+        iced::widget::container::Style {
+            background: Some(iced::Color::from(theme.cosmic().warning_color()).into()),
+            border: iced::Border { radius: 1.5.into(), ..Default::default() },
+            ..Default::default()
+        }
+    })).into()
+}
+
+// The suggested button style with its background in `color`
+fn accent_button(color: iced::Color) -> cosmic::theme::Button {
+    use cosmic::widget::button::Catalog;
+    let tinted = move |mut style: cosmic::widget::button::Style| { style.background = Some(color.into()); style };
+    cosmic::theme::Button::Custom {
+        active: Box::new(move |focused, theme: &Theme| tinted(theme.active(focused, false, &cosmic::theme::Button::Suggested))),
+        disabled: Box::new(move |theme: &Theme| tinted(theme.disabled(&cosmic::theme::Button::Suggested))),
+        hovered: Box::new(move |focused, theme: &Theme| tinted(theme.hovered(focused, false, &cosmic::theme::Button::Suggested))),
+        pressed: Box::new(move |focused, theme: &Theme| tinted(theme.pressed(focused, false, &cosmic::theme::Button::Suggested))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic::Application as _;
+
+    fn audio_at(sink_volume: u32) -> Audio {
+        let mut audio = Audio::default();
+        audio.model.sink_volume = sink_volume;
+        audio
+    }
+
+    // Closing the popup mid-drag means CommitSink never arrives
+    #[test]
+    fn closing_mid_drag_drops_the_drag_value() {
+        let mut audio = audio_at(30);
+        audio.popup = Some(window::Id::unique());
+        let _ = audio.update(Message::DragSink(60));
+        assert_eq!(audio.sink_volume(), 60);
+
+        let _ = audio.update(Message::TogglePopup);
+        assert_eq!(audio.popup, None);
+        assert_eq!(audio.sink_drag_val, None);
+        assert_eq!(audio.sink_volume(), 30);
+    }
+
+    #[test]
+    fn close_requested_resets_popup_state() {
+        let mut audio = audio_at(30);
+        let id = window::Id::unique();
+        audio.popup = Some(id);
+        let _ = audio.update(Message::OutputToggle);
+        let _ = audio.update(Message::DragSink(60));
+        let _ = audio.update(Message::DragSource(20));
+        assert_eq!(audio.is_open, IsOpen::Output);
+
+        // Another surface closing leaves the popup alone
+        let _ = audio.update(Message::CloseRequested(window::Id::unique()));
+        assert_eq!(audio.popup, Some(id));
+
+        let _ = audio.update(Message::CloseRequested(id));
+        assert_eq!(audio.popup, None);
+        assert_eq!(audio.is_open, IsOpen::None);
+        assert_eq!(audio.view_mode, ViewMode::Main);
+        assert_eq!((audio.sink_drag_val, audio.source_drag_val), (None, None));
+        assert!(audio.reveal_anim.is_none());
+    }
+
+    #[test]
+    fn far_off_volume_update_drops_a_stale_drag() {
+        let mut audio = audio_at(30);
+        let _ = audio.update(Message::DragSink(32));
+        audio.model.sink_volume = 80;
+        audio.drop_stale_drags(30, audio.model.source_volume);
+        assert_eq!(audio.sink_drag_val, None);
+        assert_eq!(audio.sink_volume(), 80);
+    }
+
+    #[test]
+    fn nearby_volume_update_keeps_the_drag() {
+        let mut audio = audio_at(30);
+        let _ = audio.update(Message::DragSink(32));
+        audio.model.sink_volume = 34;
+        audio.drop_stale_drags(30, audio.model.source_volume);
+        assert_eq!(audio.sink_drag_val, Some(32));
+    }
+
+    // The anchor can sit far below the shown level, so a long drag would pass the slider's end
+    #[test]
+    fn fine_drag_stops_at_the_maximum() {
+        let mut audio = audio_at(90);
+        audio.max_sink_volume = 100;
+        audio.modifiers = iced::keyboard::Modifiers::SHIFT;
+        assert_eq!(audio.fine_drag(DeviceKind::Sink, 10), 90);
+        assert_eq!(audio.fine_drag(DeviceKind::Sink, 100), 100);
+    }
 }
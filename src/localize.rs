@@ -18,6 +18,8 @@ pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     loader
         .load_fallback_language(&Localizations)
         .expect("Error while loading fallback language");
+    // Bidi isolation marks around numbers would show up in the percentage labels
+    loader.set_use_isolating(false);
 
     loader
 });
@@ -38,11 +40,31 @@ pub fn localizer() -> Box<dyn Localizer> {
     Box::from(DefaultLocalizer::new(&*LANGUAGE_LOADER, &Localizations))
 }
 
+/// Format a volume percentage for the active locale, falling back to `42%`.
+pub fn percent(value: u32) -> String {
+    if LANGUAGE_LOADER.has("volume-percent") {
+        crate::fl!("volume-percent", volume = value)
+    } else {
+        format!("{value}%")
+    }
+}
+
+// Languages written right-to-left
+const RTL_LANGUAGES: &[&str] = &["ar", "ckb", "dv", "fa", "he", "ps", "sd", "ug", "ur", "yi"];
+
+/// Whether the active locale is written right-to-left.
+pub fn is_rtl() -> bool {
+    LANGUAGE_LOADER
+        .current_languages()
+        .first()
+        .is_some_and(|lang| RTL_LANGUAGES.contains(&lang.language.as_str()))
+}
+
 pub fn localize() {
     let localizer = localizer();
     let requested_languages = i18n_embed::DesktopLanguageRequester::requested_languages();
 
     if let Err(error) = localizer.select(&requested_languages) {
-        eprintln!("Error while loading language for App List {error}");
+        eprintln!("Error while loading language for Audio applet {error}");
     }
 }
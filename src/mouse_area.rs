@@ -2,6 +2,7 @@
 
 use cosmic::iced::Vector;
 use cosmic::iced_core::Point;
+use std::time::{Duration, Instant};
 
 use cosmic::iced_core::{
     Clipboard, Element, Layout, Length, Rectangle, Shell, Size, Widget,
@@ -10,7 +11,25 @@ use cosmic::iced_core::{
     widget::{Operation, Tree, tree},
 };
 
+// Horizontal travel before a press-and-release counts as a swipe instead of a tap
+const SWIPE_THRESHOLD: f32 = 48.0;
+// Two touches closer than this, in time and space, make a double tap
+const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(300);
+const DOUBLE_TAP_DISTANCE: f32 = 24.0;
+
+/// Direction of a horizontal swipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Swipe {
+    Left,
+    Right,
+}
+
 /// Emit messages on mouse events.
+///
+/// By default the content sees every event first, and the area only handles what the
+/// content left uncaptured. With [`MouseArea::intercept`] the area goes first instead:
+/// events it has a handler for stop there, and everything else reaches the content.
+/// Cursor movement always reaches both, so hover state inside stays correct.
 #[allow(missing_debug_implementations)]
 // FIX: Use full paths (cosmic::Theme) to avoid name collisions with generics
 pub struct MouseArea<'a, Message, Theme = cosmic::Theme, Renderer = cosmic::iced::Renderer> {
@@ -25,6 +44,11 @@ pub struct MouseArea<'a, Message, Theme = cosmic::Theme, Renderer = cosmic::iced
     on_mouse_enter: Option<Message>,
     on_mouse_exit: Option<Message>,
     on_mouse_wheel: Option<Box<dyn Fn(mouse::ScrollDelta) -> Message + 'a>>,
+    on_mouse_move: Option<Box<dyn Fn(Point) -> Message + 'a>>,
+    on_tap: Option<Message>,
+    on_swipe: Option<Box<dyn Fn(Swipe) -> Option<Message> + 'a>>,
+    on_double_tap: Option<Message>,
+    intercept: bool,
 }
 
 impl<'a, Message, Theme, Renderer> MouseArea<'a, Message, Theme, Renderer> {
@@ -87,16 +111,56 @@ impl<'a, Message, Theme, Renderer> MouseArea<'a, Message, Theme, Renderer> {
         self.on_mouse_wheel = Some(Box::new(message));
         self
     }
+
+    /// Cursor movement inside the area, with the position relative to its top-left corner.
+    #[must_use]
+    pub fn on_mouse_move(mut self, message: impl Fn(Point) -> Message + 'a) -> Self {
+        self.on_mouse_move = Some(Box::new(message));
+        self
+    }
+
+    /// A press and release that did not travel far enough to be a swipe.
+    #[must_use]
+    pub fn on_tap(mut self, message: Message) -> Self {
+        self.on_tap = Some(message);
+        self
+    }
+
+    /// A horizontal swipe; returning `None` ignores that direction.
+    #[must_use]
+    pub fn on_swipe(mut self, message: impl Fn(Swipe) -> Option<Message> + 'a) -> Self {
+        self.on_swipe = Some(Box::new(message));
+        self
+    }
+
+    /// Two quick touches in about the same place. The first still reaches the
+    /// content as a normal press; only the second is taken.
+    #[must_use]
+    pub fn on_double_tap(mut self, message: Message) -> Self {
+        self.on_double_tap = Some(message);
+        self
+    }
+
+    /// Handle events before the content, e.g. wheel over a slider that would take it.
+    #[must_use]
+    pub fn intercept(mut self) -> Self {
+        self.intercept = true;
+        self
+    }
 }
 
 struct State {
     drag_initiated: Option<Point>,
+    swipe_start: Option<Point>,
+    last_touch: Option<(Instant, Point)>,
     is_out_of_bounds: bool,
 }
 impl Default for State {
     fn default() -> Self {
         Self {
             drag_initiated: Option::default(),
+            swipe_start: None,
+            last_touch: None,
             is_out_of_bounds: true,
         }
     }
@@ -116,6 +180,11 @@ impl<'a, Message, Theme, Renderer> MouseArea<'a, Message, Theme, Renderer> {
             on_mouse_enter: None,
             on_mouse_exit: None,
             on_mouse_wheel: None,
+            on_mouse_move: None,
+            on_tap: None,
+            on_swipe: None,
+            on_double_tap: None,
+            intercept: false,
         }
     }
 }
@@ -141,6 +210,21 @@ where
     }
 
     fn on_event(&mut self, tree: &mut Tree, event: Event, layout: Layout<'_>, cursor: mouse::Cursor, renderer: &Renderer, clipboard: &mut dyn Clipboard, shell: &mut Shell<'_, Message>, viewport: &Rectangle) -> event::Status {
+        // Checked before the content, which takes the touches a double tap is made of
+        if let Some(message) = self.on_double_tap.as_ref() {
+            if double_tapped(&event, layout, tree.state.downcast_mut::<State>()) {
+                shell.publish(message.clone());
+                return event::Status::Captured;
+            }
+        }
+        if self.intercept {
+            let status = update(self, &event, layout, cursor, shell, tree.state.downcast_mut::<State>());
+            let cursor_event = matches!(event, Event::Mouse(mouse::Event::CursorMoved { .. } | mouse::Event::CursorLeft));
+            if status == event::Status::Captured && !cursor_event {
+                return status;
+            }
+            return self.content.as_widget_mut().on_event(&mut tree.children[0], event, layout, cursor, renderer, clipboard, shell, viewport).merge(status);
+        }
         if let event::Status::Captured = self.content.as_widget_mut().on_event(&mut tree.children[0], event.clone(), layout, cursor, renderer, clipboard, shell, viewport) {
             return event::Status::Captured;
         }
@@ -180,6 +264,45 @@ fn update<Message: Clone, Theme, Renderer>(
     shell: &mut Shell<'_, Message>,
     state: &mut State,
 ) -> event::Status {
+    // Leaving the surface entirely only reports CursorLeft, never a CursorMoved outside
+    if let Event::Mouse(mouse::Event::CursorLeft) = event {
+        if !state.is_out_of_bounds {
+            state.is_out_of_bounds = true;
+            if let Some(message) = widget.on_mouse_exit.as_ref() {
+                shell.publish(message.clone());
+            }
+        }
+        return event::Status::Ignored;
+    }
+
+    // A swipe may end outside the bounds, so releases are matched before the bounds check
+    if widget.on_tap.is_some() || widget.on_swipe.is_some() {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) | Event::Touch(touch::Event::FingerPressed { .. })
+                if cursor.is_over(layout.bounds()) =>
+            {
+                state.swipe_start = pointer_position(event, cursor);
+                return event::Status::Captured;
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) | Event::Touch(touch::Event::FingerLifted { .. }) => {
+                if let Some(start) = state.swipe_start.take() {
+                    let dx = pointer_position(event, cursor).map_or(0.0, |p| p.x - start.x);
+                    let message = if dx.abs() >= SWIPE_THRESHOLD {
+                        let swipe = if dx < 0.0 { Swipe::Left } else { Swipe::Right };
+                        widget.on_swipe.as_ref().and_then(|f| f(swipe))
+                    } else {
+                        widget.on_tap.clone()
+                    };
+                    if let Some(message) = message {
+                        shell.publish(message);
+                    }
+                    return event::Status::Captured;
+                }
+            }
+            _ => {}
+        }
+    }
+
     if !cursor.is_over(layout.bounds()) {
         if !state.is_out_of_bounds {
             if widget.on_mouse_enter.as_ref().or(widget.on_mouse_exit.as_ref()).is_some() {
@@ -239,18 +362,8 @@ fn update<Message: Clone, Theme, Renderer>(
         }
     }
     
-    if let Some(message) = widget.on_mouse_enter.as_ref().or(widget.on_mouse_exit.as_ref()) {
-        if let Event::Mouse(mouse::Event::CursorMoved { .. }) = event {
-            if state.is_out_of_bounds {
-                state.is_out_of_bounds = false;
-                if widget.on_mouse_enter.is_some() {
-                    shell.publish(message.clone());
-                }
-                return event::Status::Captured;
-            }
-        }
-    }
-
+    // Ahead of move and enter, which also take the CursorMoved a drag starts with
+    let mut captured = false;
     if state.drag_initiated.is_none() && widget.on_drag.is_some() {
         if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) | Event::Touch(touch::Event::FingerPressed { .. }) = event {
             state.drag_initiated = cursor.position();
@@ -260,11 +373,36 @@ fn update<Message: Clone, Theme, Renderer>(
             if position.distance(drag_source) > 1.0 {
                 state.drag_initiated = None;
                 shell.publish(message.clone());
+                captured = true;
+            }
+        }
+    }
+
+    // Reported alongside enter, which still fires on the first move inside
+    if let Some(message) = widget.on_mouse_move.as_ref() {
+        if let Event::Mouse(mouse::Event::CursorMoved { position }) = event {
+            let bounds = layout.bounds();
+            shell.publish((message)(Point::new(position.x - bounds.x, position.y - bounds.y)));
+            captured = true;
+        }
+    }
+
+    if let Some(message) = widget.on_mouse_enter.as_ref().or(widget.on_mouse_exit.as_ref()) {
+        if let Event::Mouse(mouse::Event::CursorMoved { .. }) = event {
+            if state.is_out_of_bounds {
+                state.is_out_of_bounds = false;
+                if widget.on_mouse_enter.is_some() {
+                    shell.publish(message.clone());
+                }
                 return event::Status::Captured;
             }
         }
     }
 
+    if captured {
+        return event::Status::Captured;
+    }
+
     if let Some(message) = widget.on_mouse_wheel.as_ref() {
         if let Event::Mouse(mouse::Event::WheelScrolled { delta }) = event {
             shell.publish((message)(*delta));
@@ -273,4 +411,172 @@ fn update<Message: Clone, Theme, Renderer>(
     }
 
     event::Status::Ignored
-}
\ No newline at end of file
+}
+
+/// Whether `event` is a touch completing a double tap, remembering it otherwise.
+fn double_tapped(event: &Event, layout: Layout<'_>, state: &mut State) -> bool {
+    let Event::Touch(touch::Event::FingerPressed { position, .. }) = event else { return false };
+    if !layout.bounds().contains(*position) {
+        return false;
+    }
+    let now = Instant::now();
+    match state.last_touch.take() {
+        Some((at, last)) if now.duration_since(at) <= DOUBLE_TAP_INTERVAL && position.distance(last) <= DOUBLE_TAP_DISTANCE => true,
+        _ => {
+            state.last_touch = Some((now, *position));
+            false
+        }
+    }
+}
+
+/// Where a press or release happened; touch events carry their own position.
+fn pointer_position(event: &Event, cursor: mouse::Cursor) -> Option<Point> {
+    match event {
+        Event::Touch(touch::Event::FingerPressed { position, .. } | touch::Event::FingerLifted { position, .. }) => Some(*position),
+        _ => cursor.position(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic::iced::widget::Space;
+    use cosmic::iced_core::clipboard;
+    use event::Status::{Captured, Ignored};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Msg {
+        Press,
+        Release,
+        Drag,
+        Enter,
+        Exit,
+        Wheel(f32),
+        Move(Point),
+    }
+
+    // The null renderer is enough, as nothing is drawn
+    type Area = MouseArea<'static, Msg, cosmic::Theme, ()>;
+
+    fn area() -> Area {
+        MouseArea::new(Space::new(Length::Fill, Length::Fill))
+    }
+
+    /// Run `events` through a 100x40 area, each with the cursor at its point, and collect
+    /// the status and messages of each.
+    fn feed(widget: Area, events: &[(Event, Point)]) -> Vec<(event::Status, Vec<Msg>)> {
+        let mut widget = Element::from(widget);
+        let mut tree = Tree::new(&widget);
+        let node = layout::Node::new(Size::new(100.0, 40.0));
+        let viewport = Rectangle::with_size(Size::new(100.0, 40.0));
+        events
+            .iter()
+            .map(|(event, at)| {
+                let mut messages = Vec::new();
+                let mut shell = Shell::new(&mut messages);
+                let status = widget.as_widget_mut().on_event(
+                    &mut tree,
+                    event.clone(),
+                    Layout::new(&node),
+                    mouse::Cursor::Available(*at),
+                    &(),
+                    &mut clipboard::Null,
+                    &mut shell,
+                    &viewport,
+                );
+                (status, messages)
+            })
+            .collect()
+    }
+
+    fn pressed() -> Event {
+        Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+    }
+
+    fn released() -> Event {
+        Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+    }
+
+    fn moved(x: f32, y: f32) -> (Event, Point) {
+        let position = Point::new(x, y);
+        (Event::Mouse(mouse::Event::CursorMoved { position }), position)
+    }
+
+    fn scrolled(y: f32) -> Event {
+        Event::Mouse(mouse::Event::WheelScrolled { delta: mouse::ScrollDelta::Lines { x: 0.0, y } })
+    }
+
+    #[test]
+    fn press_then_release() {
+        let area = || area().on_press(Msg::Press).on_release(Msg::Release);
+        let inside = Point::new(10.0, 10.0);
+        assert_eq!(
+            feed(area(), &[(pressed(), inside), (released(), inside)]),
+            [(Captured, vec![Msg::Press]), (Captured, vec![Msg::Release])]
+        );
+
+        let outside = Point::new(200.0, 10.0);
+        assert_eq!(feed(area(), &[(pressed(), outside), (released(), outside)]), [(Ignored, vec![]), (Ignored, vec![])]);
+    }
+
+    #[test]
+    fn wheel_delivers_the_delta() {
+        let area = || {
+            area().on_mouse_wheel(|delta| match delta {
+                mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. } => Msg::Wheel(y),
+            })
+        };
+        assert_eq!(feed(area(), &[(scrolled(-1.0), Point::new(10.0, 10.0))]), [(Captured, vec![Msg::Wheel(-1.0)])]);
+        assert_eq!(feed(area(), &[(scrolled(-1.0), Point::new(10.0, 60.0))]), [(Ignored, vec![])]);
+    }
+
+    // Moving within the area is not a new entry, and leaving the surface counts as an exit
+    // without taking the event from others
+    #[test]
+    fn enter_and_exit_fire_on_transitions() {
+        let events = [
+            moved(10.0, 10.0),
+            moved(20.0, 10.0),
+            moved(200.0, 10.0),
+            moved(210.0, 10.0),
+            moved(10.0, 10.0),
+            (Event::Mouse(mouse::Event::CursorLeft), Point::new(10.0, 10.0)),
+        ];
+        assert_eq!(
+            feed(area().on_mouse_enter(Msg::Enter).on_mouse_exit(Msg::Exit), &events),
+            [
+                (Captured, vec![Msg::Enter]),
+                (Ignored, vec![]),
+                (Captured, vec![Msg::Exit]),
+                (Ignored, vec![]),
+                (Captured, vec![Msg::Enter]),
+                (Ignored, vec![Msg::Exit]),
+            ]
+        );
+    }
+
+    #[test]
+    fn drag_starts_past_the_threshold_once() {
+        let events = [(pressed(), Point::new(10.0, 10.0)), moved(10.5, 10.0), moved(13.0, 10.0), moved(20.0, 10.0)];
+        assert_eq!(
+            feed(area().on_drag(Msg::Drag), &events),
+            [(Ignored, vec![]), (Ignored, vec![]), (Captured, vec![Msg::Drag]), (Ignored, vec![])]
+        );
+    }
+
+    // With no move before the press, the drag's first move is also the enter
+    #[test]
+    fn drag_fires_alongside_move_and_enter() {
+        let area = area().on_drag(Msg::Drag).on_mouse_move(Msg::Move).on_mouse_enter(Msg::Enter);
+        let events = [(pressed(), Point::new(10.0, 10.0)), moved(20.0, 10.0)];
+        assert_eq!(
+            feed(area, &events),
+            [(Ignored, vec![]), (Captured, vec![Msg::Drag, Msg::Move(Point::new(20.0, 10.0)), Msg::Enter])]
+        );
+    }
+
+    #[test]
+    fn moves_report_the_cursor_position() {
+        assert_eq!(feed(area().on_mouse_move(Msg::Move), &[moved(30.0, 5.0)]), [(Captured, vec![Msg::Move(Point::new(30.0, 5.0))])]);
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{borrow::Cow, fmt::Debug, hash::Hash, path::PathBuf};
+use std::{borrow::Cow, fmt::Debug, hash::Hash, mem::discriminant, path::PathBuf, time::Duration};
 
 use cosmic::{
     iced::{self, Subscription, stream},
@@ -10,8 +10,10 @@ use cosmic::{
 use mpris2_zbus::{
     enumerator,
     media_player::MediaPlayer,
-    player::{PlaybackStatus, Player},
+    metadata::TrackId,
+    player::{LoopStatus, PlaybackStatus, Player},
 };
+use cosmic_time::Instant;
 use tokio::join;
 use urlencoding::decode;
 use zbus::{
@@ -22,18 +24,40 @@ use zbus::{
 #[derive(Clone, Debug)]
 pub struct PlayerStatus {
     pub player: Player,
+    /// The player's human-readable name, e.g. "Firefox".
+    pub app_name: Option<String>,
+    /// The `Icon=` key of the player's desktop entry: an icon name or an absolute path.
+    pub app_icon: Option<String>,
     pub icon: Option<PathBuf>,
     pub title: Option<Cow<'static, str>>,
     pub artists: Option<Vec<Cow<'static, str>>>,
+    pub album: Option<Cow<'static, str>>,
+    pub track_number: Option<i32>,
+    /// Needed to seek, so a late seek can't land in the next track.
+    pub track_id: Option<TrackId>,
     pub status: PlaybackStatus,
     pub can_pause: bool,
     pub can_play: bool,
     pub can_go_previous: bool,
     pub can_go_next: bool,
+    pub can_seek: bool,
+    pub can_control: bool,
+    /// `None` when the player does not support shuffling.
+    pub shuffle: Option<bool>,
+    /// `None` when the player does not support looping.
+    pub loop_status: Option<LoopStatus>,
+    /// Playback speed; `None` when the player does not report one.
+    pub rate: Option<f64>,
+    pub min_rate: f64,
+    pub max_rate: f64,
+    pub position: Option<Duration>,
+    pub length: Option<Duration>,
+    /// When `position` was read, so progress can be extrapolated while playing.
+    pub sampled_at: Instant,
 }
 
 impl PlayerStatus {
-    async fn new(player: Player) -> Option<Self> {
+    async fn new(player: Player, media_player: &MediaPlayer) -> Option<Self> {
         let metadata = player.metadata().await.ok()?;
         let pathname = metadata.url().unwrap_or_default();
         let pathbuf = PathBuf::from(pathname);
@@ -48,6 +72,8 @@ impl PlayerStatus {
         let artists = metadata
             .artists()
             .map(|a| a.into_iter().map(Cow::from).collect::<Vec<_>>());
+        let album = metadata.album().filter(|a| !a.is_empty()).map(Cow::from);
+        let track_number = metadata.track_number().filter(|n| *n > 0);
         let icon = metadata
             .art_url()
             .and_then(|u| url::Url::parse(&u).ok())
@@ -59,34 +85,164 @@ impl PlayerStatus {
                 }
             });
 
-        let (playback_status, can_pause, can_play, can_go_previous, can_go_next) = join!(
+        let length = metadata.length();
+        let track_id = metadata.trackid();
+
+        let (app_name, desktop_entry) = join!(media_player.identity(), media_player.desktop_entry());
+        let app_icon = match desktop_entry.ok().filter(|e| !e.is_empty()) {
+            Some(entry) => desktop_icon(&entry).await,
+            None => None,
+        };
+        let (rate, min_rate, max_rate) = join!(player.rate(), player.minimum_rate(), player.maximum_rate());
+        let (playback_status, can_pause, can_play, can_go_previous, can_go_next, can_seek, can_control, position, shuffle, loop_status) = join!(
             player.playback_status(),
             player.can_pause(),
             player.can_play(),
             player.can_go_previous(),
-            player.can_go_next()
+            player.can_go_next(),
+            player.can_seek(),
+            player.can_control(),
+            player.position(),
+            player.shuffle(),
+            player.loop_status()
         );
         Some(Self {
+            app_name: app_name.ok().filter(|n| !n.is_empty()),
+            app_icon,
             icon,
             title,
             artists,
+            album,
+            track_number,
+            track_id,
             status: playback_status.unwrap_or(PlaybackStatus::Stopped),
             can_pause: can_pause.unwrap_or_default(),
             can_play: can_play.unwrap_or_default(),
             can_go_previous: can_go_previous.unwrap_or_default(),
             can_go_next: can_go_next.unwrap_or_default(),
+            can_seek: can_seek.unwrap_or_default(),
+            can_control: can_control.unwrap_or_default(),
+            shuffle: shuffle.ok(),
+            loop_status: loop_status.ok(),
+            rate: rate.ok(),
+            // The spec makes both bounds 1.0 for players that can't change speed
+            min_rate: min_rate.unwrap_or(1.0),
+            max_rate: max_rate.unwrap_or(1.0),
+            position: position.ok(),
+            length,
+            sampled_at: Instant::now(),
             player,
         })
     }
+
+    /// The current playback position, extrapolated from the last sample while playing.
+    pub fn position_now(&self) -> Option<Duration> {
+        let position = self.position?;
+        let position = if matches!(self.status, PlaybackStatus::Playing) {
+            position + self.sampled_at.elapsed().mul_f64(self.rate.unwrap_or(1.0).max(0.0))
+        } else {
+            position
+        };
+        Some(self.length.map_or(position, |length| position.min(length)))
+    }
+}
+
+/// The `Icon=` of desktop entry `entry`, from the first data directory that has it.
+async fn desktop_icon(entry: &str) -> Option<String> {
+    let home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")));
+    let dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_owned());
+    let dirs = home.into_iter().chain(dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    for dir in dirs {
+        let path = dir.join("applications").join(format!("{entry}.desktop"));
+        if let Ok(contents) = tokio::fs::read_to_string(&path).await {
+            return icon_key(&contents);
+        }
+    }
+    None
+}
+
+/// The `Icon` key of a desktop file's `[Desktop Entry]` group.
+fn icon_key(contents: &str) -> Option<String> {
+    let mut in_entry = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+        } else if let Some(icon) = line.strip_prefix("Icon").and_then(|l| l.trim_start().strip_prefix('=')).filter(|_| in_entry) {
+            let icon = icon.trim();
+            return (!icon.is_empty()).then(|| icon.to_owned());
+        }
+    }
+    None
+}
+
+/// The last value sent, so repeats of it can be dropped.
+struct LastSent<T>(Option<T>);
+
+impl<T> Default for LastSent<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T: Clone> LastSent<T> {
+    /// `value` when it differs from the last value sent by `same`, which it then becomes.
+    fn changed(&mut self, value: T, same: impl Fn(&T, &T) -> bool) -> Option<T> {
+        if self.0.as_ref().is_some_and(|last| same(last, &value)) {
+            return None;
+        }
+        self.0 = Some(value.clone());
+        Some(value)
+    }
+}
+
+fn same_player(a: &Option<PlayerStatus>, b: &Option<PlayerStatus>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.same_as(b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
 }
 
+// Position drift beyond which a re-read counts as a seek rather than normal playback
+const POSITION_TOLERANCE: Duration = Duration::from_secs(1);
+
+impl PlayerStatus {
+    /// Whether `other` would display the same as `self`, allowing for playback progress.
+    fn same_as(&self, other: &PlayerStatus) -> bool {
+        let position_matches = match (self.position_now(), other.position) {
+            (Some(a), Some(b)) => a.abs_diff(b) < POSITION_TOLERANCE,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.player.inner().destination() == other.player.inner().destination()
+            && self.app_name == other.app_name
+            && self.app_icon == other.app_icon
+            && self.icon == other.icon
+            && self.title == other.title
+            && self.artists == other.artists
+            && self.album == other.album
+            && self.track_number == other.track_number
+            && discriminant(&self.status) == discriminant(&other.status)
+            && (self.can_pause, self.can_play, self.can_go_previous, self.can_go_next, self.can_seek, self.can_control)
+                == (other.can_pause, other.can_play, other.can_go_previous, other.can_go_next, other.can_seek, other.can_control)
+            && self.length == other.length
+            && self.shuffle == other.shuffle
+            && self.loop_status.as_ref().map(discriminant) == other.loop_status.as_ref().map(discriminant)
+            && self.rate == other.rate
+            && position_matches
+    }
+}
+
+/// Follows the most recently active player, or the one whose bus name starts with
+/// `preferred` while it is running.
 pub fn mpris_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
     id: I,
+    preferred: Option<String>,
 ) -> iced::Subscription<MprisUpdate> {
     Subscription::run_with_id(
-        id,
+        (id, preferred.clone()),
         stream::channel(50, move |mut output| async move {
-            run(&mut output).await;
+            run(&mut output, preferred).await;
             let _ = output.send(MprisUpdate::Finished).await;
             futures::future::pending().await
         }),
@@ -96,7 +252,6 @@ pub fn mpris_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
 #[derive(Clone, Debug)]
 struct MprisPlayer {
     player: Player,
-    #[allow(dead_code)]
     media_player: MediaPlayer,
 }
 
@@ -124,9 +279,14 @@ pub enum MprisUpdate {
 pub enum MprisRequest {
     Play,
     Pause,
+    Stop,
     Next,
     Previous,
     Raise,
+    SetShuffle(bool),
+    SetLoopStatus(LoopStatus),
+    SetRate(f64),
+    SetPosition(Duration),
 }
 
 struct State {
@@ -134,6 +294,7 @@ struct State {
     enumerator_stream:
         Box<dyn futures::Stream<Item = zbus::Result<enumerator::Event>> + Unpin + Send>,
     players: Vec<MprisPlayer>,
+    preferred: Option<String>,
     active_player: Option<MprisPlayer>,
     active_player_metadata_stream: Option<Box<dyn futures::Stream<Item = ()> + Unpin + Send>>,
     any_player_state_stream:
@@ -150,7 +311,7 @@ fn filter_firefox_players(players: &mut Vec<MprisPlayer>) {
 }
 
 impl State {
-    async fn new() -> Result<Self, zbus::Error> {
+    async fn new(preferred: Option<String>) -> Result<Self, zbus::Error> {
         let conn = Connection::session().await?;
 
         let enumerator = enumerator::Enumerator::new(&conn).await?;
@@ -177,6 +338,7 @@ impl State {
             conn,
             enumerator_stream: Box::new(enumerator_stream),
             players,
+            preferred,
             active_player: None,
             active_player_metadata_stream: None,
             any_player_state_stream: futures::stream::select_all([]),
@@ -208,7 +370,10 @@ impl State {
     }
 
     async fn update_active_player(&mut self) {
-        let new_active_player = find_active(&self.players).await;
+        let new_active_player = match self.preferred.as_deref().and_then(|prefix| self.players.iter().find(|p| p.name().starts_with(prefix))) {
+            Some(preferred) => Some(preferred),
+            None => find_active(&self.players).await,
+        };
         if self.active_player.as_ref().map(|p| p.name()) != new_active_player.map(|p| p.name()) {
             self.active_player = new_active_player.cloned();
             if let Some(player) = new_active_player {
@@ -217,11 +382,14 @@ impl State {
                     player.player.receive_can_play_changed().await,
                     player.player.receive_can_go_previous_changed().await,
                     player.player.receive_can_go_next_changed().await,
+                    player.player.receive_shuffle_changed().await,
                 ]);
                 let metadata_changed = player.player.receive_metadata_changed().await;
+                let loop_changed = player.player.receive_loop_status_changed().await;
+                let rate_changed = player.player.receive_rate_changed().await;
                 let stream = futures::stream::select(
-                    controls_changed.map(|_| ()),
-                    metadata_changed.map(|_| ()),
+                    futures::stream::select(controls_changed.map(|_| ()), metadata_changed.map(|_| ())),
+                    futures::stream::select(loop_changed.map(|_| ()), rate_changed.map(|_| ())),
                 );
                 self.active_player_metadata_stream = Some(Box::new(stream));
             } else {
@@ -239,8 +407,8 @@ impl State {
     }
 }
 
-async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>) {
-    let mut state = match State::new().await {
+async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>, preferred: Option<String>) {
+    let mut state = match State::new(preferred).await {
         Ok(state) => state,
         Err(err) => {
             tracing::error!("Failed to monitor for mpris clients: {}", err);
@@ -248,15 +416,22 @@ async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>) {
         }
     };
 
+    // The last update sent, so players appearing and vanishing or unrelated
+    // property changes don't repeat it; `None` stands for `Setup`
+    let mut last_sent = LastSent::default();
     loop {
-        if let Some(player) = &state.active_player {
-            if let Some(player_status) = PlayerStatus::new(player.player.clone()).await {
-                _ = output.send(MprisUpdate::Player(player_status)).await;
-            } else {
-                tracing::error!("Failed to get player status.");
-            }
-        } else {
-            let _ = output.send(MprisUpdate::Setup).await;
+        let current = match &state.active_player {
+            Some(player) => match PlayerStatus::new(player.player.clone(), &player.media_player).await {
+                Some(player_status) => Some(Some(player_status)),
+                None => {
+                    tracing::error!("Failed to get player status.");
+                    None
+                }
+            },
+            None => Some(None),
+        };
+        if let Some(update) = current.and_then(|status| last_sent.changed(status, same_player)) {
+            let _ = output.send(update.map_or(MprisUpdate::Setup, MprisUpdate::Player)).await;
         }
 
         let metadata_changed_next = OptionFuture::from(
@@ -312,3 +487,45 @@ async fn find_active<'a>(players: &'a [MprisPlayer]) -> Option<&'a MprisPlayer>
 
     best.1
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_desktop_entry_icon() {
+        let desktop = "[Desktop Entry]\nName=Player\nIcon = org.example.Player\n\n[Desktop Action new]\nIcon=other\n";
+        assert_eq!(icon_key(desktop).as_deref(), Some("org.example.Player"));
+        assert_eq!(icon_key("[Desktop Action new]\nIcon=other\n"), None);
+        assert_eq!(icon_key("[Desktop Entry]\nIcon=/opt/player/icon.png\n").as_deref(), Some("/opt/player/icon.png"));
+    }
+
+    #[test]
+    fn duplicate_updates_are_sent_once() {
+        let mut last_sent = LastSent::default();
+        let sent: Vec<_> = [1, 1, 2, 2, 2, 1]
+            .into_iter()
+            .filter_map(|v| last_sent.changed(v, |a, b| a == b))
+            .collect();
+        assert_eq!(sent, [1, 2, 1]);
+    }
+
+    // Players appearing and vanishing repeat `Setup`, standing in here as `None`
+    #[test]
+    fn repeated_setup_is_sent_once() {
+        let mut last_sent = LastSent::default();
+        let sent: Vec<_> = [None, None, Some("a"), Some("a"), None, None]
+            .into_iter()
+            .filter_map(|v| last_sent.changed(v, |a, b| a == b))
+            .collect();
+        assert_eq!(sent, [None, Some("a"), None]);
+    }
+
+    #[test]
+    fn nearby_values_count_as_unchanged() {
+        let mut last_sent = LastSent::default();
+        assert_eq!(last_sent.changed(10, |a: &i32, b: &i32| a.abs_diff(*b) < 2), Some(10));
+        assert_eq!(last_sent.changed(11, |a: &i32, b: &i32| a.abs_diff(*b) < 2), None);
+        assert_eq!(last_sent.changed(13, |a: &i32, b: &i32| a.abs_diff(*b) < 2), Some(13));
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Desktop notifications through `org.freedesktop.Notifications`.
+
+use std::collections::HashMap;
+use zbus::{Connection, Proxy, zvariant::Value};
+
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+// Let the server pick its default timeout
+const DEFAULT_TIMEOUT: i32 = -1;
+
+/// Show `summary` from `app_name`, replacing the notification `replaces` (0 for none),
+/// and return the id of the one shown; 0 when no notification server is available.
+pub async fn show(app_name: String, summary: String, icon: &'static str, replaces: u32) -> u32 {
+    match send(&app_name, &summary, icon, replaces).await {
+        Ok(id) => id,
+        Err(err) => {
+            tracing::debug!("Failed to send notification: {}", err);
+            0
+        }
+    }
+}
+
+async fn send(app_name: &str, summary: &str, icon: &str, replaces: u32) -> zbus::Result<u32> {
+    let conn = Connection::session().await?;
+    let notifications = Proxy::new(&conn, NOTIFICATIONS, NOTIFICATIONS_PATH, NOTIFICATIONS).await?;
+    let hints: HashMap<&str, Value<'_>> = HashMap::new();
+    notifications
+        .call("Notify", &(app_name, replaces, icon, summary, "", Vec::<&str>::new(), hints, DEFAULT_TIMEOUT))
+        .await
+}
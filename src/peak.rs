@@ -0,0 +1,99 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Peak levels of what a device plays or records, sampled with `parec`.
+
+use std::process::Stdio;
+
+use cosmic::{
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, channel::mpsc::Sender},
+};
+use tokio::{io::AsyncReadExt, process::Command};
+
+// Low enough to keep `parec` cheap, high enough to catch short peaks
+const RATE: u32 = 8000;
+// Frames in each reported block, 50 ms at `RATE`
+const BLOCK_FRAMES: usize = 400;
+
+/// Peaks of `device` per channel, from 0.0 to 1.0 at full scale, every 50 ms.
+/// `device` is a source name, or `@DEFAULT_MONITOR@` for what the default output plays.
+pub fn levels(device: &'static str, channels: u8) -> iced::Subscription<Vec<f32>> {
+    let channels = channels.max(1);
+    Subscription::run_with_id(
+        ("peak", device, channels),
+        stream::channel(4, move |mut output| async move {
+            if let Err(err) = record(&mut output, device, channels).await {
+                tracing::warn!("Failed to sample {device}: {err}");
+            }
+            futures::future::pending().await
+        }),
+    )
+}
+
+async fn record(output: &mut Sender<Vec<f32>>, device: &str, channels: u8) -> std::io::Result<()> {
+    // Ends with the subscription, when the future holding it is dropped
+    let mut child = Command::new("parec")
+        .args([
+            &format!("--device={device}"),
+            "--raw",
+            "--format=s16le",
+            &format!("--rate={RATE}"),
+            &format!("--channels={channels}"),
+            "--latency-msec=50",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let Some(mut stdout) = child.stdout.take() else {
+        return Ok(());
+    };
+    let mut block = vec![0; BLOCK_FRAMES * usize::from(channels) * 2];
+    loop {
+        stdout.read_exact(&mut block).await?;
+        if output.send(peaks(&block, channels.into())).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// The largest magnitude per channel of interleaved s16le `samples`, as a fraction of full scale.
+fn peaks(samples: &[u8], channels: usize) -> Vec<f32> {
+    let mut peaks = vec![0.0_f32; channels];
+    for (i, sample) in samples.chunks_exact(2).enumerate() {
+        let level = f32::from(i16::from_le_bytes([sample[0], sample[1]]).unsigned_abs()) / 32768.0;
+        let peak = &mut peaks[i % channels];
+        *peak = peak.max(level);
+    }
+    peaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interleaved(frames: &[[i16; 2]]) -> Vec<u8> {
+        frames.iter().flatten().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn peaks_per_channel() {
+        let samples = interleaved(&[[0, -16384], [8192, 100], [-8192, 0]]);
+        assert_eq!(peaks(&samples, 2), [0.25, 0.5]);
+    }
+
+    #[test]
+    fn full_scale_is_one() {
+        let samples = interleaved(&[[i16::MIN, i16::MAX]]);
+        let peaks = peaks(&samples, 2);
+        assert_eq!(peaks[0], 1.0);
+        assert!(peaks[1] > 0.999);
+    }
+
+    #[test]
+    fn mono_takes_every_sample() {
+        let samples = interleaved(&[[100, -3000]]);
+        assert_eq!(peaks(&samples, 1), [3000.0 / 32768.0]);
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Whether the user is away, from the logind session's lock and idle hints.
+
+use cosmic::{
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, StreamExt, channel::mpsc::Sender},
+};
+use zbus::{Connection, Proxy};
+
+const LOGIN1: &str = "org.freedesktop.login1";
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+const SESSION: &str = "org.freedesktop.login1.Session";
+
+/// Emits `true` when the session locks or goes idle, and `false` once it is neither.
+pub fn away() -> iced::Subscription<bool> {
+    Subscription::run_with_id(
+        SESSION_PATH,
+        stream::channel(4, |mut output| async move {
+            if let Err(err) = watch(&mut output).await {
+                tracing::error!("Failed to watch the login session: {}", err);
+            }
+            futures::future::pending().await
+        }),
+    )
+}
+
+async fn watch(output: &mut Sender<bool>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let session = Proxy::new(&conn, LOGIN1, SESSION_PATH, SESSION).await?;
+    let locked = session.receive_property_changed::<bool>("LockedHint").await;
+    let idle = session.receive_property_changed::<bool>("IdleHint").await;
+    let mut changes = futures::stream::select(locked.map(|_| ()), idle.map(|_| ()));
+
+    let mut last = None;
+    while changes.next().await.is_some() {
+        let locked: bool = session.get_property("LockedHint").await.unwrap_or(false);
+        let idle: bool = session.get_property("IdleHint").await.unwrap_or(false);
+        let away = locked || idle;
+        if last != Some(away) {
+            last = Some(away);
+            if output.send(away).await.is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
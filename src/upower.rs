@@ -0,0 +1,81 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Battery levels of audio peripherals as reported by UPower.
+
+use cosmic::iced_futures::futures::future::join_all;
+use zbus::{Connection, Proxy, zvariant::OwnedObjectPath};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const UPOWER_DEVICE: &str = "org.freedesktop.UPower.Device";
+
+// UPower device types: headset, speakers, headphones, other audio
+const AUDIO_TYPES: [u32; 4] = [17, 18, 19, 21];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Battery {
+    /// The device model, which matches the Bluetooth alias for wireless headsets.
+    pub model: String,
+    /// The Bluetooth address, for devices UPower sees over Bluetooth.
+    pub address: Option<String>,
+    pub percentage: u8,
+}
+
+/// Batteries of present audio devices; empty when UPower is unavailable.
+pub async fn audio_batteries() -> Vec<Battery> {
+    match query().await {
+        Ok(batteries) => batteries,
+        Err(err) => {
+            tracing::debug!("Failed to query UPower: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+async fn query() -> zbus::Result<Vec<Battery>> {
+    let conn = Connection::system().await?;
+    let upower = Proxy::new(&conn, UPOWER, UPOWER_PATH, UPOWER).await?;
+    let paths: Vec<OwnedObjectPath> = upower.call("EnumerateDevices", &()).await?;
+
+    let devices = paths.into_iter().map(|path| battery(&conn, path));
+    Ok(join_all(devices).await.into_iter().flatten().collect())
+}
+
+/// The battery of one audio device; `None` for other devices, and for ones
+/// missing a property, so a single odd device doesn't hide the rest.
+async fn battery(conn: &Connection, path: OwnedObjectPath) -> Option<Battery> {
+    let device = Proxy::new(conn, UPOWER, path, UPOWER_DEVICE).await.ok()?;
+    let kind: u32 = device.get_property("Type").await.ok()?;
+    let present: bool = device.get_property("IsPresent").await.unwrap_or(true);
+    if !AUDIO_TYPES.contains(&kind) || !present {
+        return None;
+    }
+    let model: String = device.get_property("Model").await.ok()?;
+    let percentage: f64 = device.get_property("Percentage").await.ok()?;
+    let native_path: String = device.get_property("NativePath").await.unwrap_or_default();
+    (!model.is_empty()).then(|| Battery {
+        model,
+        address: bluetooth_address(&native_path),
+        percentage: percentage.round().clamp(0.0, 100.0) as u8,
+    })
+}
+
+/// The address in a BlueZ native path such as `/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF`.
+fn bluetooth_address(native_path: &str) -> Option<String> {
+    let (_, dev) = native_path.split_once("dev_")?;
+    let address = dev.split('/').next()?.replace('_', ":");
+    (address.len() == 17).then_some(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_address_from_native_path() {
+        assert_eq!(bluetooth_address("/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF").as_deref(), Some("AA:BB:CC:DD:EE:FF"));
+        assert_eq!(bluetooth_address("battery-sony_controller_battery_aa:bb"), None);
+        assert_eq!(bluetooth_address(""), None);
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Per-node audio state read from `wpctl`, for devices other than the default and for
+//! scripting, and combined sinks through PipeWire's PulseAudio module support. Volume
+//! and mute changes go through [`crate::audio_command`].
+
+use std::{collections::HashMap, process::Stdio};
+
+use cosmic::{
+    iced::{self, Subscription, stream},
+    iced_futures::futures::{self, SinkExt, channel::mpsc::Sender},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: u32,
+    /// The node description, as listed by the settings model.
+    pub name: String,
+    /// Volume in percent.
+    pub volume: u32,
+    pub muted: bool,
+}
+
+/// Sinks with their PipeWire ids and volumes; empty when `wpctl` is unavailable.
+pub async fn sinks() -> Vec<Node> {
+    let Ok(output) = Command::new("wpctl").arg("status").output().await else {
+        return Vec::new();
+    };
+    parse_section(&String::from_utf8_lossy(&output.stdout), "Sinks:")
+}
+
+/// Volume of the default sink in percent; `None` when `wpctl` is unavailable.
+pub async fn default_sink_volume() -> Option<u32> {
+    default_sink_state().await.map(|(volume, _)| volume)
+}
+
+/// Volume in percent and mute state of the default sink.
+pub async fn default_sink_state() -> Option<(u32, bool)> {
+    let output = Command::new("wpctl").args(["get-volume", "@DEFAULT_AUDIO_SINK@"]).output().await.ok()?;
+    // Prints e.g. `Volume: 0.40 [MUTED]`
+    let output = String::from_utf8_lossy(&output.stdout);
+    let volume: f32 = output.split_whitespace().nth(1)?.parse().ok()?;
+    Some(((volume * 100.0).round() as u32, output.contains("[MUTED]")))
+}
+
+/// Whether a stream is playing through the default sink; `false` when `pactl` is unavailable.
+pub async fn default_sink_running() -> bool {
+    let Ok(default) = Command::new("pactl").arg("get-default-sink").output().await else {
+        return false;
+    };
+    let default = String::from_utf8_lossy(&default.stdout).trim().to_owned();
+    let Ok(sinks) = Command::new("pactl").args(["list", "short", "sinks"]).output().await else {
+        return false;
+    };
+    // Lines are `id name driver format state`, separated by tabs
+    String::from_utf8_lossy(&sinks.stdout).lines().any(|l| {
+        let fields: Vec<&str> = l.split('\t').collect();
+        fields.get(1) == Some(&default.as_str()) && fields.last() == Some(&"RUNNING")
+    })
+}
+
+/// Fires on each change `pactl subscribe` reports to a sink or a stream, which includes
+/// streams starting and stopping; never fires when `pactl` is unavailable.
+pub fn sink_changes() -> iced::Subscription<()> {
+    Subscription::run_with_id(
+        "pactl-subscribe",
+        stream::channel(4, |mut output| async move {
+            if let Err(err) = watch_sinks(&mut output).await {
+                tracing::warn!("Failed to watch sinks: {err}");
+            }
+            futures::future::pending().await
+        }),
+    )
+}
+
+async fn watch_sinks(output: &mut Sender<()>) -> std::io::Result<()> {
+    // Ends with the subscription, when the future holding it is dropped
+    let mut child = Command::new("pactl")
+        .env("LC_ALL", "C")
+        .arg("subscribe")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(());
+    };
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if is_sink_event(&line) && output.send(()).await.is_err() {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+// Lines look like `Event 'change' on sink-input #83`
+fn is_sink_event(line: &str) -> bool {
+    line.rsplit_once(" on ")
+        .and_then(|(_, object)| object.split_once(" #"))
+        .is_some_and(|(facility, _)| matches!(facility, "sink" | "sink-input"))
+}
+
+/// Sample rate and format of the default sink, e.g. `48000 Hz, S24LE`.
+pub async fn default_sink_format() -> Option<String> {
+    let default = Command::new("pactl").arg("get-default-sink").output().await.ok()?;
+    let default = String::from_utf8_lossy(&default.stdout).trim().to_owned();
+    devices("sinks").await.into_iter().find(|d| d.name == default)?.format()
+}
+
+/// A sink, source or stream as `pactl list` describes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Device {
+    /// The node name, e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo`.
+    pub name: String,
+    /// The name the settings model lists.
+    pub description: String,
+    /// The sink a monitor source records from.
+    pub monitor_of: Option<String>,
+    /// Volume of the first channel in percent.
+    pub volume: Option<u32>,
+    pub muted: bool,
+    /// e.g. `s24le 2ch 48000Hz`.
+    pub sample_spec: Option<String>,
+    pub properties: HashMap<String, String>,
+}
+
+impl Device {
+    /// Sample rate and format from the sample specification, e.g. `48000 Hz, S24LE`.
+    pub fn format(&self) -> Option<String> {
+        let mut fields = self.sample_spec.as_deref()?.split_whitespace();
+        let format = fields.next()?.to_uppercase();
+        let rate = fields.find_map(|f| f.strip_suffix("Hz"))?;
+        Some(format!("{rate} Hz, {format}"))
+    }
+
+    /// Channel count from the sample specification.
+    pub fn channels(&self) -> Option<u8> {
+        self.sample_spec.as_deref()?.split_whitespace().find_map(|f| f.strip_suffix("ch")?.parse().ok())
+    }
+
+    /// Whether this is a source recording what an output plays.
+    pub fn is_monitor(&self) -> bool {
+        self.monitor_of.is_some() || self.properties.get("device.class").is_some_and(|c| c == "monitor")
+    }
+}
+
+/// Channel count of the default source; `None` when `pactl` is unavailable.
+pub async fn default_source_channels() -> Option<u8> {
+    let default = Command::new("pactl").arg("get-default-source").output().await.ok()?;
+    let default = String::from_utf8_lossy(&default.stdout).trim().to_owned();
+    devices("sources").await.into_iter().find(|d| d.name == default)?.channels()
+}
+
+/// Descriptions of the sources that are loopbacks of outputs.
+pub async fn monitor_sources() -> Vec<String> {
+    devices("sources").await.into_iter().filter(Device::is_monitor).map(|d| d.description).collect()
+}
+
+/// Bluetooth addresses of Bluetooth sinks and sources, keyed by description.
+pub async fn bluetooth_addresses() -> HashMap<String, String> {
+    let (sinks, sources) = tokio::join!(devices("sinks"), devices("sources"));
+    sinks.into_iter().chain(sources)
+        .filter_map(|d| Some((d.properties.get("api.bluez5.address")?.clone(), d.description)))
+        .map(|(address, description)| (description, address))
+        .collect()
+}
+
+/// A stream an application plays, for following the focused window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppStream {
+    pub node: Node,
+    // Lowercased app ids and the binary name of the stream's application
+    keys: Vec<String>,
+}
+
+impl AppStream {
+    fn new(device: Device) -> Option<Self> {
+        let id = device.properties.get("object.id")?.parse().ok()?;
+        let prop = |key: &str| device.properties.get(key).filter(|v| !v.is_empty());
+        let name = prop("application.name").or_else(|| prop("media.name")).cloned().unwrap_or_default();
+        let keys = ["application.id", "pipewire.access.portal.app_id", "application.process.binary"]
+            .into_iter()
+            .filter_map(|key| prop(key).map(|v| v.to_lowercase()))
+            .collect();
+        Some(Self {
+            node: Node { id, name, volume: device.volume.unwrap_or(0), muted: device.muted },
+            keys,
+        })
+    }
+
+    /// Whether the stream plays for the window with `app_id`, which has to match the
+    /// stream's full app id, such as `org.mozilla.firefox`, or its binary, such as `firefox`.
+    pub fn belongs_to(&self, app_id: &str) -> bool {
+        let app_id = app_id.to_lowercase();
+        self.keys.contains(&app_id)
+    }
+}
+
+/// Streams applications are playing; empty when `pactl` is unavailable.
+pub async fn app_streams() -> Vec<AppStream> {
+    devices("sink-inputs").await.into_iter().filter_map(AppStream::new).collect()
+}
+
+/// `kind` (`sinks`, `sources` or `sink-inputs`) as `pactl` lists them; empty when it is unavailable.
+async fn devices(kind: &str) -> Vec<Device> {
+    // Field names are translated outside the C locale
+    let Ok(output) = Command::new("pactl").env("LC_ALL", "C").args(["list", kind]).output().await else {
+        return Vec::new();
+    };
+    parse_devices(&String::from_utf8_lossy(&output.stdout))
+}
+
+// Each device is a block starting at an unindented `Sink #47` line, with tab-indented
+// fields and a `Properties:` list of `key = "value"` lines.
+fn parse_devices(listing: &str) -> Vec<Device> {
+    let mut devices = Vec::new();
+    for line in listing.lines() {
+        if !line.starts_with(char::is_whitespace) && line.contains(" #") {
+            devices.push(Device::default());
+            continue;
+        }
+        let Some(device) = devices.last_mut() else { continue };
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name: ") {
+            device.name = name.to_owned();
+        } else if let Some(description) = line.strip_prefix("Description: ") {
+            device.description = description.to_owned();
+        } else if let Some(sink) = line.strip_prefix("Monitor of Sink: ") {
+            device.monitor_of = (sink != "n/a").then(|| sink.to_owned());
+        } else if let Some(volume) = line.strip_prefix("Volume: ") {
+            // e.g. `front-left: 26214 /  40% / -23.88 dB,   front-right: ...`
+            device.volume = volume.split_whitespace().find_map(|f| f.strip_suffix('%')?.parse().ok());
+        } else if let Some(mute) = line.strip_prefix("Mute: ") {
+            device.muted = mute == "yes";
+        } else if let Some(spec) = line.strip_prefix("Sample Specification: ") {
+            device.sample_spec = Some(spec.to_owned());
+        } else if let Some((key, value)) = line.split_once(" = ") {
+            device.properties.insert(key.to_owned(), value.trim_matches('"').to_owned());
+        }
+    }
+    devices
+}
+
+/// Description of the sink created by [`combine_sinks`].
+pub const COMBINED_SINK: &str = "Combined output";
+
+/// Create a combined sink playing to all of `ids`, replacing the one this applet made
+/// as module `previous`, and return the new module's id.
+pub async fn combine_sinks(ids: Vec<u32>, previous: Option<u32>) -> Option<u32> {
+    let mut names = Vec::with_capacity(ids.len());
+    for id in ids {
+        names.push(node_name(id).await?);
+    }
+    // Combined sinks made elsewhere are left alone
+    if let Some(id) = previous {
+        unload_module(id).await;
+    }
+    let output = Command::new("pactl")
+        .args([
+            "load-module",
+            "module-combine-sink",
+            "sink_name=cosmic_combined",
+            &format!("slaves={}", names.join(",")),
+            &format!("sink_properties=device.description=\"{COMBINED_SINK}\""),
+        ])
+        .output()
+        .await
+        .ok()?;
+    // `load-module` prints the new module's index
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().parse().ok()).flatten()
+}
+
+/// Unload PulseAudio module `id`, such as a combined sink.
+pub async fn unload_module(id: u32) {
+    let status = Command::new("pactl").args(["unload-module", &id.to_string()]).status().await;
+    if !status.is_ok_and(|s| s.success()) {
+        tracing::warn!("Failed to unload module {id}");
+    }
+}
+
+// The `node.name` property, e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo`
+async fn node_name(id: u32) -> Option<String> {
+    let output = Command::new("wpctl").args(["inspect", &id.to_string()]).output().await.ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|l| {
+        let value = l.trim().trim_start_matches('*').trim().strip_prefix("node.name = ")?;
+        Some(value.trim_matches('"').to_owned())
+    })
+}
+
+// Entries look like ` │  *   47. Built-in Audio Analog Stereo [vol: 0.40 MUTED]`, under
+// a `Sinks:` heading in the `Audio` tree and up to the next blank tree line.
+fn parse_section(status: &str, heading: &str) -> Vec<Node> {
+    let trim = |l: &str| l.trim_matches(|c: char| c.is_whitespace() || "│├└─*".contains(c)).to_owned();
+    let lines = status
+        .lines()
+        .skip_while(|l| trim(l) != "Audio")
+        .skip_while(|l| trim(l) != heading)
+        .skip(1);
+
+    let mut nodes = Vec::new();
+    for line in lines {
+        let line = trim(line);
+        if line.is_empty() {
+            break;
+        }
+        let Some((id, rest)) = line.split_once(". ") else { continue };
+        let Ok(id) = id.trim().parse() else { continue };
+        let (name, volume, muted) = match rest.rsplit_once("[vol:") {
+            Some((name, vol)) => (name, vol.split_whitespace().next().and_then(|v| v.trim_end_matches(']').parse::<f32>().ok()), vol.contains("MUTED")),
+            None => (rest, None, false),
+        };
+        nodes.push(Node {
+            id,
+            name: name.trim().to_owned(),
+            volume: volume.map_or(0, |v| (v * 100.0).round() as u32),
+            muted,
+        });
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &str = "Source #47
+\tState: SUSPENDED
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tDescription: Monitor of Built-in Audio Analog Stereo
+\tMonitor of Sink: alsa_output.pci-0000_00_1f.3.analog-stereo
+\tProperties:
+\t\tdevice.class = \"monitor\"
+\t\tmedia.class = \"Audio/Sink\"
+
+Source #52
+\tState: RUNNING
+\tName: alsa_input.usb-0d8c_USB_Sound_Device-00.mono-fallback
+\tDescription: USB Sound Device Mono
+\tSample Specification: s16le 1ch 48000Hz
+\tMonitor of Sink: n/a
+\tProperties:
+\t\tdevice.class = \"sound\"
+\t\tmedia.class = \"Audio/Source\"
+";
+
+    #[test]
+    fn parses_pactl_blocks() {
+        let devices = parse_devices(SOURCES);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[1].name, "alsa_input.usb-0d8c_USB_Sound_Device-00.mono-fallback");
+        assert_eq!(devices[1].description, "USB Sound Device Mono");
+        assert_eq!(devices[1].properties.get("media.class").map(String::as_str), Some("Audio/Source"));
+    }
+
+    #[test]
+    fn tells_monitors_apart() {
+        let monitors: Vec<bool> = parse_devices(SOURCES).iter().map(Device::is_monitor).collect();
+        assert_eq!(monitors, [true, false]);
+    }
+
+    #[test]
+    fn reads_channel_count() {
+        let channels: Vec<Option<u8>> = parse_devices(SOURCES).iter().map(Device::channels).collect();
+        assert_eq!(channels, [None, Some(1)]);
+    }
+
+    #[test]
+    fn formats_the_sample_spec() {
+        let formats: Vec<Option<String>> = parse_devices(SOURCES).iter().map(Device::format).collect();
+        assert_eq!(formats, [None, Some("48000 Hz, S16LE".to_owned())]);
+    }
+
+    const SINK_INPUTS: &str = "Sink Input #83
+\tDriver: protocol-native.c
+\tSink: 47
+\tMute: no
+\tVolume: front-left: 26214 /  40% / -23.88 dB,   front-right: 26214 /  40% / -23.88 dB
+\tProperties:
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.binary = \"firefox\"
+\t\tobject.id = \"91\"
+
+Sink Input #84
+\tMute: yes
+\tVolume: mono: 65536 / 100% / 0.00 dB
+\tProperties:
+\t\tapplication.name = \"\"
+\t\tmedia.name = \"Playback\"
+\t\tpipewire.access.portal.app_id = \"com.spotify.Client\"
+\t\tobject.id = \"95\"
+";
+
+    #[test]
+    fn reads_stream_volume_and_mute() {
+        let streams: Vec<AppStream> = parse_devices(SINK_INPUTS).into_iter().filter_map(AppStream::new).collect();
+        let nodes: Vec<&Node> = streams.iter().map(|s| &s.node).collect();
+        assert_eq!(nodes, [
+            &Node { id: 91, name: "Firefox".to_owned(), volume: 40, muted: false },
+            &Node { id: 95, name: "Playback".to_owned(), volume: 100, muted: true },
+        ]);
+    }
+
+    #[test]
+    fn matches_streams_to_window_app_ids() {
+        let streams: Vec<AppStream> = parse_devices(SINK_INPUTS).into_iter().filter_map(AppStream::new).collect();
+        assert!(streams[0].belongs_to("firefox"));
+        assert!(streams[1].belongs_to("com.spotify.Client"));
+        // Neither the last part of another app's id nor a part of this one's is enough
+        assert!(!streams[0].belongs_to("org.example.firefox"));
+        assert!(!streams[1].belongs_to("Client"));
+        assert!(!streams[1].belongs_to("spotify"));
+    }
+
+    #[test]
+    fn picks_sink_and_stream_events() {
+        let lines = [
+            "Event 'change' on sink #47",
+            "Event 'new' on sink-input #83",
+            "Event 'change' on source #52",
+            "Event 'remove' on source-output #90",
+            "Event 'change' on server #-1",
+        ];
+        let picked: Vec<bool> = lines.into_iter().map(is_sink_event).collect();
+        assert_eq!(picked, [true, true, false, false, false]);
+    }
+}